@@ -0,0 +1,4 @@
+#[tokio::main]
+async fn main() {
+    rand_sht::http_client::run_http_client().await;
+}
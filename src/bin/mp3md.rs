@@ -0,0 +1,3 @@
+fn main() {
+    rand_sht::metadata_editor::run_metadata_editor();
+}
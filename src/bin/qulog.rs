@@ -0,0 +1,7 @@
+#[tokio::main]
+async fn main() {
+    if let Err(err) = rand_sht::quick_logger::run_qulog().await {
+        eprintln!("qulog: {}", err);
+        std::process::exit(err.exit_code());
+    }
+}
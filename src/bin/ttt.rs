@@ -0,0 +1,3 @@
+fn main() {
+    rand_sht::tictactoe::run_tictactoe();
+}
@@ -17,6 +17,17 @@ enum Error {
     JsonDeserialize,
 }
 
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidURL => write!(f, "invalid url"),
+            Error::Network(status) => write!(f, "network error: {}", status),
+            Error::Unknown => write!(f, "unknown error"),
+            Error::JsonDeserialize => write!(f, "unexpected json response"),
+        }
+    }
+}
+
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Self {
         if err.is_status() {
@@ -38,6 +49,16 @@ enum ErrorStatus {
     Other(u16),
 }
 
+impl std::fmt::Display for ErrorStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorStatus::NotFound => write!(f, "not found"),
+            ErrorStatus::InternalServer => write!(f, "internal server error"),
+            ErrorStatus::Other(code) => write!(f, "status {}", code),
+        }
+    }
+}
+
 impl ErrorStatus {
     fn from(code: u16) -> ErrorStatus {
         match code {
@@ -51,13 +72,13 @@ impl ErrorStatus {
 pub async fn run_http_client() {
     match get_message_back("hello world").await {
         Ok(msg) => println!("{msg}"),
-        Err(err) => println!("An error accured {:?}", err),
+        Err(err) => println!("An error accured: {}", err),
     }
 
     //TODO: Errors are not get handled properly
     match post_something().await {
         Ok(_) => println!("posted successfully"),
-        Err(err) => println!("An error accured {:?}", err),
+        Err(err) => println!("An error accured: {}", err),
     };
 }
 
@@ -66,7 +87,7 @@ async fn get_message_back(message: &str) -> Result<String, Error> {
     queries.insert("message", message);
     let url = match reqwest::Url::parse_with_params(Urls::GET, &queries) {
         Ok(url) => url,
-        Err(err) => return Err(Error::InvalidURL),
+        Err(_) => return Err(Error::InvalidURL),
     };
     //Intentionally manual decoding is used here
     reqwest::get(url)
@@ -112,6 +133,6 @@ async fn post_something() -> Result<(), Error> {
         .inspect(|res| println!("{:?}", res))?
         .json::<SomthingToPostResponse>()
         .await
-        .map(|_| ())
-        .map_err(|e| Error::from(e))
+        .map(|res| println!("{:?}", res.json))
+        .map_err(Error::from)
 }
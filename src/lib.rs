@@ -1,6 +1,6 @@
 pub mod http_client;
 pub mod linked_list;
 pub mod metadata_editor;
+pub mod quick_logger;
 pub mod stack;
 pub mod tictactoe;
-pub mod quick_logger;
@@ -11,6 +11,12 @@ pub struct LinkedList<T: Debug> {
     current: Option<Rc<RefCell<LLNode<T>>>>,
 }
 
+impl<T: Debug> Default for LinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T: Debug> LinkedList<T> {
     pub fn display(&self) {
         let mut current = Rc::clone(&self.head);
@@ -24,7 +30,7 @@ impl<T: Debug> LinkedList<T> {
     pub fn new() -> LinkedList<T> {
         let head = Rc::new(RefCell::new(LLNode::EOL));
         LinkedList {
-            head: head,
+            head,
             current: None,
         }
     }
@@ -38,7 +44,7 @@ impl<T: Debug> LinkedList<T> {
                 )))
             }
             LLNode::Node(_, next) => {
-                let mut last = Rc::clone(&next);
+                let mut last = Rc::clone(next);
 
                 while let LLNode::Node(_, next) = last.clone().borrow().deref() {
                     last = Rc::clone(next);
@@ -72,24 +78,22 @@ impl<T: Debug + Copy> Iterator for LinkedList<T> {
         match self.current.clone() {
             Some(ref current) => match current.deref().borrow().deref() {
                 LLNode::Node(value, next) => {
-                    self.current = Some(Rc::clone(&next));
-                    return Some(*value);
+                    self.current = Some(Rc::clone(next));
+                    Some(*value)
                 }
                 LLNode::EOL => {
                     self.current = None;
-                    return None;
+                    None
                 }
             },
-            None => {
-                match *self.head.borrow() {
-                    LLNode::EOL => return None,
-                    LLNode::Node(ref v, ref n) => {
-                        self.current = Some(Rc::clone(n));
-                        return Some(*v);
-                    }
-                };
-            }
-        };
+            None => match *self.head.borrow() {
+                LLNode::EOL => None,
+                LLNode::Node(ref v, ref n) => {
+                    self.current = Some(Rc::clone(n));
+                    Some(*v)
+                }
+            },
+        }
     }
 }
 
@@ -104,9 +108,7 @@ mod linkedlist_test {
     fn linked_list_create() {
         let linked_list: LinkedList<i32> = LinkedList::new();
         match linked_list.head.deref().borrow().deref() {
-            LLNode::Node(_, _) => {
-                assert!(true);
-            }
+            LLNode::Node(_, _) => {}
             LLNode::EOL => {}
         };
     }
@@ -124,7 +126,7 @@ mod linkedlist_test {
         linked_list.push(0);
         linked_list.push(1);
 
-        let mut iter = linked_list.into_iter();
+        let iter = linked_list.by_ref();
         assert_eq!(iter.next(), Some(1));
         assert_eq!(iter.next(), Some(0));
         assert_eq!(iter.next(), Some(10));
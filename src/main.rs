@@ -1,10 +1,71 @@
+use clap::{Parser, Subcommand};
+use rand_sht::{metadata_editor::Mp3MetaDataCli, quick_logger::LogCreateCli};
+
+#[derive(Debug, Parser)]
+#[command(name = "rand_sht")]
+struct RandShtCli {
+    #[command(subcommand)]
+    command: RandShtCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum RandShtCommand {
+    /// Quick log persister with sqlite
+    #[command(name = "qulog")]
+    Qulog(LogCreateCli),
+
+    /// Mp3 tag editor
+    #[command(name = "mp3")]
+    Mp3(Mp3MetaDataCli),
+
+    /// Tic-tac-toe game in terminal
+    #[command(name = "ttt")]
+    Ttt,
+
+    /// Small http client playground
+    #[command(name = "http")]
+    Http,
+}
+
 #[tokio::main]
 async fn main() {
-    rand_sht::quick_logger::run_qulog().await;
+    let args = RandShtCli::parse();
+
+    match args.command {
+        RandShtCommand::Qulog(args) => {
+            if let Err(err) = rand_sht::quick_logger::run_qulog_with(args).await {
+                eprintln!("qulog: {}", err);
+                std::process::exit(err.exit_code());
+            }
+        }
+        RandShtCommand::Mp3(args) => rand_sht::metadata_editor::run_metadata_editor_with(args),
+        RandShtCommand::Ttt => rand_sht::tictactoe::run_tictactoe(),
+        RandShtCommand::Http => rand_sht::http_client::run_http_client().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn test_cli_is_valid() {
+        RandShtCli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_cli_dispatches_nested_commands() {
+        let args = RandShtCli::try_parse_from(["rand_sht", "qulog", "log", "hello", "-t", "a,b"]);
+        assert!(matches!(args.unwrap().command, RandShtCommand::Qulog(_)));
 
-    // rand_sht::tictactoe::run_tictactoe();
+        let args = RandShtCli::try_parse_from(["rand_sht", "mp3", "show", "song.mp3"]);
+        assert!(matches!(args.unwrap().command, RandShtCommand::Mp3(_)));
 
-    // rand_sht::http_client::run_http_client();
+        let args = RandShtCli::try_parse_from(["rand_sht", "ttt"]);
+        assert!(matches!(args.unwrap().command, RandShtCommand::Ttt));
 
-    // rand_sht::metadata_editor::run_metadata_editor();
+        assert!(RandShtCli::try_parse_from(["rand_sht", "log", "hello"]).is_err());
+    }
 }
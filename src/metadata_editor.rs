@@ -1,6 +1,6 @@
 use std::{fs, path::PathBuf, str::FromStr};
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use id3::{
     frame::{Picture, PictureType},
     Error, ErrorKind, Tag, TagLike,
@@ -13,7 +13,7 @@ use id3::{
 
 #[derive(Debug, Parser)]
 #[command(name = "mp3md")]
-struct Mp3MetaDataCli {
+pub struct Mp3MetaDataCli {
     #[command(subcommand)]
     command: Mp3MetaDataCLiCommand,
 }
@@ -23,68 +23,50 @@ enum Mp3MetaDataCLiCommand {
         path: String,
     },
     Edit {
-        #[arg(long)]
-        album: Option<String>,
+        #[command(flatten)]
+        edit: Mp3MetaDataEdit,
 
-        #[arg(long)]
-        artist: Option<String>,
+        path: String,
+    },
+}
 
-        #[arg(long)]
-        coverpath: Option<String>,
+#[derive(Debug, Args)]
+struct Mp3MetaDataEdit {
+    #[arg(long)]
+    album: Option<String>,
 
-        #[arg(long)]
-        genre: Option<String>,
+    #[arg(long)]
+    artist: Option<String>,
 
-        #[arg(long)]
-        duration: Option<u32>,
+    #[arg(long)]
+    coverpath: Option<String>,
 
-        #[arg(long)]
-        title: Option<String>,
+    #[arg(long)]
+    genre: Option<String>,
 
-        #[arg(long)]
-        destination: Option<String>,
+    #[arg(long)]
+    duration: Option<u32>,
 
-        path: String,
-    },
+    #[arg(long)]
+    title: Option<String>,
+
+    #[arg(long)]
+    destination: Option<String>,
 }
 
 pub fn run_metadata_editor() {
-    let args = Mp3MetaDataCli::parse();
+    run_metadata_editor_with(Mp3MetaDataCli::parse());
+}
+
+pub fn run_metadata_editor_with(args: Mp3MetaDataCli) {
     match args.command {
         Mp3MetaDataCLiCommand::Show { path } => show_metadata(path),
-        Mp3MetaDataCLiCommand::Edit {
-            album,
-            artist,
-            coverpath,
-            genre,
-            duration,
-            title,
-            destination,
-            path,
-        } => {
+        Mp3MetaDataCLiCommand::Edit { edit, path } => {
             let pathbuf = PathBuf::from_str(&path).expect("Invalid path");
             if pathbuf.is_dir() {
-                edit_metadata_batch(
-                    &album,
-                    &artist,
-                    &coverpath,
-                    &genre,
-                    &duration,
-                    &title,
-                    &destination,
-                    &path,
-                );
+                edit_metadata_batch(&edit, &path);
             } else {
-                edit_metadata(
-                    &album,
-                    &artist,
-                    &coverpath,
-                    &genre,
-                    &duration,
-                    &title,
-                    &destination,
-                    &path,
-                );
+                edit_metadata(&edit, &path);
             }
         }
     }
@@ -106,61 +88,33 @@ fn show_metadata(path: String) {
     println!("genre: {}", tag.genre().unwrap_or("--"));
 }
 
-fn edit_metadata_batch(
-    album: &Option<String>,
-    artist: &Option<String>,
-    coverpath: &Option<String>,
-    genre: &Option<String>,
-    duration: &Option<u32>,
-    title: &Option<String>,
-    destination: &Option<String>,
-    path: &String,
-) {
-    let path = PathBuf::from_str(&path).expect("Invalid path");
+fn edit_metadata_batch(edit: &Mp3MetaDataEdit, path: &str) {
+    let path = PathBuf::from_str(path).expect("Invalid path");
 
     fs::read_dir(path)
         .expect("Can't read the given path")
-        .into_iter()
         .map_while(|e| e.ok())
         .filter(|e| !e.path().is_dir())
         .map(|e| e.path())
         .map_while(|e| e.to_str().map(|s| s.to_string()))
         .for_each(|s| {
-            edit_metadata(
-                album,
-                artist,
-                coverpath,
-                genre,
-                duration,
-                title,
-                destination,
-                &s,
-            );
+            edit_metadata(edit, &s);
         });
 }
 
-fn edit_metadata(
-    album: &Option<String>,
-    artist: &Option<String>,
-    coverpath: &Option<String>,
-    genre: &Option<String>,
-    duration: &Option<u32>,
-    title: &Option<String>,
-    destination: &Option<String>,
-    path: &String,
-) {
+fn edit_metadata(edit: &Mp3MetaDataEdit, path: &str) {
     let pathbuf = PathBuf::from_str(path).unwrap();
-    let name = pathbuf.components().last().unwrap().as_os_str();
+    let name = pathbuf.components().next_back().unwrap().as_os_str();
 
-    let temp_path = match destination {
+    let temp_path = match &edit.destination {
         Some(destination) => PathBuf::from_str(destination.as_str()).expect("Invalid path"),
         None => std::env::current_dir().expect("Can't access to current path"),
     }
-    .join(&name);
+    .join(name);
 
     fs::copy(&pathbuf, &temp_path).expect("Unable to copy file");
 
-    let mut tag = match Tag::read_from_path(&temp_path.as_path()) {
+    let mut tag = match Tag::read_from_path(temp_path.as_path()) {
         Ok(tag) => tag,
         Err(Error {
             kind: ErrorKind::NoTag,
@@ -169,28 +123,28 @@ fn edit_metadata(
         Err(err) => panic!("{err:?}"),
     };
 
-    if let Some(album) = album {
+    if let Some(album) = &edit.album {
         tag.set_album(album);
     }
 
-    if let Some(artist) = artist {
+    if let Some(artist) = &edit.artist {
         tag.set_artist(artist);
     }
 
-    if let Some(genre) = genre {
+    if let Some(genre) = &edit.genre {
         tag.set_genre(genre);
     }
 
-    if let Some(title) = title {
+    if let Some(title) = &edit.title {
         tag.set_title(title);
     }
 
-    if let Some(duration) = duration {
+    if let Some(duration) = &edit.duration {
         tag.set_duration(*duration);
     }
 
-    if let Some(coverpath) = coverpath {
-        match std::fs::read(&coverpath) {
+    if let Some(coverpath) = &edit.coverpath {
+        match std::fs::read(coverpath) {
             Ok(image_data) => {
                 let description = String::new();
                 let mime_type = String::new();
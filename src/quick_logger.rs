@@ -1,25 +1,101 @@
-use std::{fmt::Debug, path::Path};
-
-use clap::{Parser, Subcommand, ValueEnum};
+mod attachment;
+mod clock;
+mod config;
+mod dates;
+mod error;
+mod export;
+mod import;
+mod input;
+mod level;
+mod migrations;
+mod ranges;
+mod search;
+mod stats;
+mod store;
+mod tags;
+mod tui;
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
 //log struct
 
-use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
-use sqlx::{sqlite::SqlitePool, FromRow, Sqlite};
+use chrono::{DateTime, Days, FixedOffset, Local, Months, Offset, TimeZone, Utc, Weekday};
+
+pub use attachment::QuLogAttachment;
+use clock::{Clock, SystemClock};
+pub use error::QuLogError;
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
+};
+pub use level::QuLogLevel;
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions},
+    FromRow, QueryBuilder, Sqlite, SqliteConnection,
+};
+pub use store::{JsonlLogStore, LogStore, LogStream, MemoryLogStore, QuLogStore, StoreFuture};
 
 // const SQL_DATE_FORMAT_
 
-struct QuLog {
-    text: String,
-    tags: QuLogTags,
-    create_date: DateTime<Local>,
+/// A log entry, saved and read back through [`QuLogStore`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuLog {
+    /// `None` until the log is saved
+    pub id: Option<i64>,
+    pub text: String,
+    pub tags: QuLogTags,
+    pub level: QuLogLevel,
+    /// Host, script or anything else the log came from
+    pub source: Option<String>,
+    /// Free `key=value` pairs, stored as a JSON object
+    pub fields: BTreeMap<String, String>,
+    /// Files attached with [`QuLogStore::attach`], ignored when the log is inserted
+    pub attachments: Vec<QuLogAttachment>,
+    /// When the log was written, with the offset of the machine it was written on
+    pub create_date: DateTime<FixedOffset>,
+    /// When the log was last edited
+    pub updated_date: Option<DateTime<Utc>>,
+}
+
+impl QuLog {
+    /// An `info` log with `text` and `tags` written now.
+    pub fn new(text: impl Into<String>, tags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        QuLog {
+            id: None,
+            text: text.into(),
+            tags: QuLogTags(tags.into_iter().map(Into::into).collect()),
+            level: QuLogLevel::default(),
+            source: None,
+            fields: BTreeMap::new(),
+            attachments: Vec::new(),
+            create_date: Local::now().fixed_offset(),
+            updated_date: None,
+        }
+    }
 }
 
-struct QuLogTags(Vec<String>);
+/// Tags of a log, in the order they were added.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct QuLogTags(pub Vec<String>);
+
+impl From<Vec<String>> for QuLogTags {
+    fn from(tags: Vec<String>) -> Self {
+        QuLogTags(tags)
+    }
+}
 
 impl From<String> for QuLogTags {
     fn from(value: String) -> Self {
         let tags = value
             .split(",")
+            .map(str::trim)
             .filter(|s| !s.is_empty())
             .map(String::from)
             .collect();
@@ -27,9 +103,9 @@ impl From<String> for QuLogTags {
     }
 }
 
-impl Into<String> for QuLogTags {
-    fn into(self) -> String {
-        self.0.join(",")
+impl From<QuLogTags> for String {
+    fn from(tags: QuLogTags) -> Self {
+        tags.0.join(",")
     }
 }
 
@@ -39,41 +115,378 @@ impl QuLogTags {
     }
 }
 
+/// How a tag filter is applied when more than one tag is given.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, ValueEnum)]
+pub enum QuLogTagMatch {
+    /// Logs having every one of the given tags
+    #[default]
+    All,
+    /// Logs having at least one of the given tags
+    Any,
+}
+
+/// Order logs are selected in, by the date they were written.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, ValueEnum)]
+pub enum QuLogSort {
+    /// Oldest first
+    #[default]
+    Asc,
+    /// Newest first
+    Desc,
+}
+
+/// Which logs a query, count or delete applies to, the default matches every log.
+/// `limit` and `offset` count in the `sort` order, so the newest 10 logs are
+/// `.sort(QuLogSort::Desc).limit(10)`.
+#[derive(Debug, Default, Clone)]
+pub struct QuLogFilter {
+    ids: Vec<i64>,
+    tags: QuLogTags,
+    tag_match: QuLogTagMatch,
+    /// Empty matches every level
+    levels: Vec<QuLogLevel>,
+    source: Option<String>,
+    fields: Vec<(String, String)>,
+    start_date: Option<DateTime<Local>>,
+    end_date: Option<DateTime<Local>>,
+    sort: QuLogSort,
+    limit: Option<u32>,
+    offset: u32,
+}
+
+impl QuLogFilter {
+    pub fn builder() -> QuLogFilterBuilder {
+        QuLogFilterBuilder::default()
+    }
+
+    /// Whether `log` is selected, for stores filtering in memory like `push_filter` does in SQL.
+    fn matches(&self, log: &QuLog) -> bool {
+        let after_start = self.start_date.is_none_or(|start| log.create_date >= start);
+        let before_end = self.end_date.is_none_or(|end| log.create_date < end);
+
+        let mut tags = self.tags.0.clone();
+        tags.sort();
+        tags.dedup();
+        let found = tags.iter().filter(|tag| log.tags.0.contains(tag)).count();
+        let tagged = match self.tag_match {
+            _ if tags.is_empty() => true,
+            QuLogTagMatch::All => found == tags.len(),
+            QuLogTagMatch::Any => found >= 1,
+        };
+
+        let listed = self.ids.is_empty() || log.id.is_some_and(|id| self.ids.contains(&id));
+        let leveled = self.levels.is_empty() || self.levels.contains(&log.level);
+        let sourced = self
+            .source
+            .as_ref()
+            .is_none_or(|source| log.source.as_ref() == Some(source));
+        let fielded = self
+            .fields
+            .iter()
+            .all(|(key, value)| log.fields.get(key) == Some(value));
+
+        after_start && before_end && tagged && listed && leveled && sourced && fielded
+    }
+
+    fn is_paged(&self) -> bool {
+        self.limit.is_some() || self.offset > 0
+    }
+
+    /// The page of `logs` matching the filter, in `sort` order, like `push_order` does in SQL.
+    fn select(&self, logs: impl IntoIterator<Item = QuLog>) -> Vec<QuLog> {
+        let mut logs: Vec<QuLog> = logs.into_iter().filter(|log| self.matches(log)).collect();
+        logs.sort_by_key(|log| (log.create_date, log.id));
+        if self.sort == QuLogSort::Desc {
+            logs.reverse();
+        }
+        logs.into_iter()
+            .skip(self.offset as usize)
+            .take(self.limit.map_or(usize::MAX, |limit| limit as usize))
+            .collect()
+    }
+}
+
+/// Builds a [`QuLogFilter`], every condition left out matches every log.
+///
+/// ```
+/// use chrono::{Duration, Local};
+/// use rand_sht::quick_logger::{QuLogFilter, QuLogTagMatch};
+///
+/// let last_hour = QuLogFilter::builder()
+///     .tags(["work", "meeting"])
+///     .tag_match(QuLogTagMatch::All)
+///     .start_date(Local::now() - Duration::hours(1))
+///     .build();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct QuLogFilterBuilder {
+    filter: QuLogFilter,
+}
+
+impl QuLogFilterBuilder {
+    /// Only the logs with these ids
+    pub fn ids(mut self, ids: impl IntoIterator<Item = i64>) -> Self {
+        self.filter.ids = ids.into_iter().collect();
+        self
+    }
+
+    /// Logs tagged with these tags, how many of them is set by `tag_match`
+    pub fn tags(mut self, tags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.filter.tags = QuLogTags(tags.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn tag_match(mut self, tag_match: QuLogTagMatch) -> Self {
+        self.filter.tag_match = tag_match;
+        self
+    }
+
+    /// Logs with one of these levels
+    pub fn levels(mut self, levels: impl IntoIterator<Item = QuLogLevel>) -> Self {
+        self.filter.levels = levels.into_iter().collect();
+        self
+    }
+
+    /// Logs with `level` or a more important one
+    pub fn min_level(self, level: QuLogLevel) -> Self {
+        self.levels(QuLogLevel::ALL.into_iter().filter(|l| *l >= level))
+    }
+
+    /// Logs written by `source`
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.filter.source = Some(source.into());
+        self
+    }
+
+    /// Logs whose field `key` is `value`, every field given must match
+    pub fn field(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.filter.fields.push((key.into(), value.into()));
+        self
+    }
+
+    /// Logs written at or after `date`
+    pub fn start_date<Tz: TimeZone>(mut self, date: DateTime<Tz>) -> Self {
+        self.filter.start_date = Some(date.with_timezone(&Local));
+        self
+    }
+
+    /// Logs written before `date`
+    pub fn end_date<Tz: TimeZone>(mut self, date: DateTime<Tz>) -> Self {
+        self.filter.end_date = Some(date.with_timezone(&Local));
+        self
+    }
+
+    pub fn sort(mut self, sort: QuLogSort) -> Self {
+        self.filter.sort = sort;
+        self
+    }
+
+    /// At most `limit` logs
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.filter.limit = Some(limit);
+        self
+    }
+
+    /// Skips the first `offset` logs
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.filter.offset = offset;
+        self
+    }
+
+    pub fn build(self) -> QuLogFilter {
+        self.filter
+    }
+}
+
 struct DBConfig {
     in_memory: bool,
+    path: PathBuf,
+    path_source: config::DBPathSource,
+    backend: QuLogBackend,
+}
+
+/// How the logs at `DBConfig::path` are stored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum QuLogBackend {
+    /// A SQLite database, every command works with it
+    Sqlite,
+    /// A `.jsonl` file logs are appended to, see [`JsonlLogStore`]
+    Jsonl,
+}
+
+impl QuLogBackend {
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("jsonl") => QuLogBackend::Jsonl,
+            _ => QuLogBackend::Sqlite,
+        }
+    }
+}
+
+impl DBConfig {
+    #[cfg(test)]
+    fn in_memory() -> Self {
+        DBConfig {
+            in_memory: true,
+            path: PathBuf::from(":memory:"),
+            path_source: config::DBPathSource::Default,
+            backend: QuLogBackend::Sqlite,
+        }
+    }
+
+    /// Database file picked by `--db`, `$QULOG_DB`, the config file or the XDG data directory.
+    fn resolve(
+        cli_db: Option<PathBuf>,
+        config: &config::QuLogConfig,
+        config_path: Option<&Path>,
+    ) -> Self {
+        let env = |key: &str| std::env::var(key).ok();
+        let (path, path_source) = config::resolve_db_path(cli_db, &env, config, config_path);
+
+        DBConfig {
+            in_memory: path == Path::new(":memory:"),
+            backend: QuLogBackend::from_path(&path),
+            path,
+            path_source,
+        }
+    }
+
+    /// Opens the store for `backend`, SQLite databases are migrated unless `migrate` is false.
+    async fn open(&self, migrate: bool) -> Result<Box<dyn LogStore>, QuLogError> {
+        match self.backend {
+            QuLogBackend::Jsonl => Ok(Box::new(JsonlLogStore::new(&self.path))),
+            QuLogBackend::Sqlite => {
+                let store = QuLogStore::connect(self).await?;
+                if migrate {
+                    store.migrate().await?;
+                }
+                Ok(Box::new(store))
+            }
+        }
+    }
+}
+
+/// Changes applied to a log by [`QuLogStore::update`] and `qulog edit`.
+#[derive(Debug, Default, Clone)]
+pub struct QuLogUpdate {
+    /// The new text, `None` keeps it
+    pub text: Option<String>,
+    pub add_tags: QuLogTags,
+    pub remove_tags: QuLogTags,
+}
+
+impl QuLogUpdate {
+    /// Text and tags of `log` once updated, added tags go after the ones it kept.
+    fn apply(&self, log: &QuLog) -> (String, Vec<String>) {
+        let text = self.text.clone().unwrap_or_else(|| log.text.clone());
+        let mut tags: Vec<String> = log
+            .tags
+            .0
+            .iter()
+            .filter(|tag| !self.remove_tags.0.contains(tag))
+            .cloned()
+            .collect();
+        for tag in &self.add_tags.0 {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        (text, tags)
+    }
 }
 
 #[derive(Debug, FromRow)]
 struct QuLogDBO {
+    #[sqlx(default)]
+    id: i64,
     text: String,
     #[sqlx(default)]
     tags: String,
-    create_date: DateTime<Local>,
+    #[sqlx(default)]
+    level: QuLogLevel,
+    #[sqlx(default)]
+    source: Option<String>,
+    /// JSON object of the fields
+    #[sqlx(default)]
+    fields: String,
+    /// JSON array of the attachments, read only
+    #[sqlx(default)]
+    attachments: String,
+    /// Stored in UTC so dates compare and sort as text, whatever offset they were written with
+    create_date: DateTime<Utc>,
+    /// Seconds east of UTC `create_date` was written with
+    #[sqlx(default)]
+    create_offset: i32,
+    #[sqlx(default)]
+    updated_date: Option<DateTime<Utc>>,
 }
 
 impl From<&QuLog> for QuLogDBO {
     fn from(log: &QuLog) -> Self {
         QuLogDBO {
+            id: log.id.unwrap_or_default(),
             text: log.text.clone(),
             tags: log.tags.0.join(","),
-            create_date: log.create_date,
+            level: log.level,
+            source: log.source.clone(),
+            fields: serde_json::to_string(&log.fields).unwrap_or_default(),
+            attachments: serde_json::to_string(&log.attachments).unwrap_or_default(),
+            create_date: log.create_date.to_utc(),
+            create_offset: log.create_date.offset().local_minus_utc(),
+            updated_date: log.updated_date,
         }
     }
 }
 
-impl Into<QuLog> for QuLogDBO {
-    fn into(self) -> QuLog {
+impl From<QuLogDBO> for QuLog {
+    fn from(db_model: QuLogDBO) -> Self {
         QuLog {
-            text: self.text.clone(),
-            tags: QuLogTags::from(self.tags),
-            create_date: self.create_date,
+            id: Some(db_model.id),
+            text: db_model.text,
+            tags: QuLogTags::from(db_model.tags),
+            level: db_model.level,
+            source: db_model.source,
+            fields: serde_json::from_str(&db_model.fields).unwrap_or_default(),
+            attachments: serde_json::from_str(&db_model.attachments).unwrap_or_default(),
+            create_date: db_model
+                .create_date
+                .with_timezone(&FixedOffset::east_opt(db_model.create_offset).unwrap_or(Utc.fix())),
+            updated_date: db_model.updated_date,
         }
     }
 }
 
+#[derive(Debug, FromRow)]
+struct QuLogEditDBO {
+    old_text: String,
+    new_text: String,
+    old_tags: String,
+    new_tags: String,
+    edit_date: DateTime<Utc>,
+}
+
 #[derive(Debug, Parser)]
 #[command(name = "qulog")]
-struct LogCreateCli {
+pub struct LogCreateCli {
+    /// Database file, defaults to $QULOG_DB, db_path of the config file
+    /// or $XDG_DATA_HOME/qulog/logs.db
+    #[arg(long, global = true)]
+    db: Option<PathBuf>,
+
+    /// chrono strftime format dates are printed with, defaults to date_format of the config file
+    #[arg(long, global = true, value_parser = parse_date_format)]
+    date_format: Option<String>,
+
+    /// First day of the week for this-week and last-week, defaults to week_start of the
+    /// config file or monday
+    #[arg(long, global = true, value_parser = ranges::parse_week_start)]
+    week_start: Option<Weekday>,
+
+    /// Time zone dates are printed in: local, utc, original (the offset each log was
+    /// written with) or a name like Europe/Berlin, defaults to time_zone of the config file
+    #[arg(long, global = true, value_parser = dates::parse_time_zone)]
+    tz: Option<dates::QuLogTimeZone>,
+
     #[command(subcommand)]
     command: QuLogCommand,
 }
@@ -81,385 +494,1468 @@ struct LogCreateCli {
 #[derive(Debug, Subcommand)]
 enum QuLogCommand {
     Log {
-        text: String,
+        /// Text of the log, - reads it from stdin, with --edit it's the draft the editor opens
+        #[arg(required_unless_present = "edit")]
+        text: Option<String>,
         #[arg(long, short)]
         tags: Option<String>,
+        /// Write the log in $VISUAL or $EDITOR
+        #[arg(long)]
+        edit: bool,
+        #[arg(long, value_enum, default_value_t)]
+        level: QuLogLevel,
+        /// Host, script or anything else the log comes from
+        #[arg(long)]
+        source: Option<String>,
+        /// A key=value pair stored with the log, can be repeated
+        #[arg(long = "field", value_parser = level::parse_field)]
+        fields: Vec<(String, String)>,
+        /// A file to attach to the log, can be repeated
+        #[arg(long = "attach")]
+        attachments: Vec<PathBuf>,
     },
 
     Show {
+        #[command(flatten)]
+        filter: QuLogFilterArgs,
+        #[command(flatten)]
+        page: QuLogPageArgs,
+    },
+
+    Export {
+        #[command(flatten)]
+        filter: QuLogFilterArgs,
+        /// Directory to export to, defaults to export_dir of the config file or ./exports
         #[arg(long)]
-        tags: Option<String>,
+        to: Option<String>,
+        /// Stylesheet of the html table, defaults to css_path of the config file
+        #[arg(long)]
+        css: Option<PathBuf>,
+        /// tinytemplate file of the html export, defaults to template_path of the config file
+        #[arg(long)]
+        template: Option<PathBuf>,
+        #[arg(short, long, value_enum, default_value_t)]
+        format: export::QuLogExportFormat,
+    },
+
+    /// Change the text or tags of a log, the previous version is kept in its history
+    #[command(group(
+        clap::ArgGroup::new("changes")
+            .required(true)
+            .multiple(true)
+            .args(["text", "add_tags", "remove_tags"])
+    ))]
+    Edit {
+        id: i64,
+        #[arg(long)]
+        text: Option<String>,
+        #[arg(long = "add-tag")]
+        add_tags: Vec<String>,
+        #[arg(long = "remove-tag")]
+        remove_tags: Vec<String>,
+    },
+
+    /// Show the edit history of a log
+    History { id: i64 },
+
+    /// Delete the logs matching the filters
+    Delete {
+        #[command(flatten)]
+        filter: QuLogFilterArgs,
+        /// Delete without asking for confirmation
         #[arg(long, short)]
-        start_date: Option<String>,
+        yes: bool,
+    },
+
+    /// Delete logs older than a retention period
+    Prune {
+        /// Retention period like 90d, 12w, 6m or 1y
+        #[arg(long)]
+        older_than: QuLogRetention,
+        /// Delete without asking for confirmation
         #[arg(long, short)]
-        end_date: Option<String>,
-        #[arg(value_enum)]
-        date_range: Option<QuLogCommandDateRange>,
+        yes: bool,
     },
 
-    Export {
+    /// Count the logs matching the filters by day, week or month, with their top tags,
+    /// longest streak, busiest hour and a calendar
+    Stats {
+        #[command(flatten)]
+        filter: QuLogFilterArgs,
+        /// Length of the periods logs are counted by
+        #[arg(long, value_enum, default_value_t)]
+        by: stats::QuLogStatsPeriod,
+        /// Number of tags listed
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        /// Print the stats as JSON
         #[arg(long)]
-        tags: Option<String>,
+        json: bool,
+    },
+
+    /// Browse, filter, edit and delete logs in an interactive terminal view
+    Tui,
+
+    /// Full-text search over log texts
+    Search {
+        /// FTS5 query: words, "exact phrases", prefix* and AND/OR/NOT
+        query: String,
+        #[arg(long, short, default_value_t = 20)]
+        limit: i64,
+    },
+
+    /// Import logs from a csv, json, ndjson or jrnl file
+    Import {
+        file: PathBuf,
+        /// Defaults to the format matching the file extension
+        #[arg(short, long, value_enum)]
+        format: Option<import::QuLogImportFormat>,
+    },
+
+    /// Read the files attached to logs
+    Attachment {
+        #[command(subcommand)]
+        command: QuLogAttachmentCommand,
+    },
+
+    /// List, rename, merge and delete tags across every log
+    Tags {
+        #[command(subcommand)]
+        command: QuLogTagsCommand,
+    },
+
+    /// Read and change the config file
+    Config {
+        #[command(subcommand)]
+        command: QuLogConfigCommand,
+    },
+
+    /// Inspect and migrate the log database schema
+    Db {
+        #[command(subcommand)]
+        command: QuLogDbCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum QuLogAttachmentCommand {
+    /// Write an attachment to a file
+    Get {
+        id: i64,
+        /// File or directory to write to, - for stdout, defaults to <ID>-<NAME>
+        /// in the current directory
         #[arg(long, short)]
-        start_date: Option<String>,
+        output: Option<PathBuf>,
+        /// Overwrite the file when it exists
+        #[arg(long, short)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum QuLogTagsCommand {
+    /// Print every tag with the number of logs having it
+    List,
+
+    /// Rename a tag on every log having it
+    Rename {
+        #[arg(value_parser = tags::parse_tag)]
+        old: String,
+        #[arg(value_parser = tags::parse_tag)]
+        new: String,
+    },
+
+    /// Replace tags by another one on every log having them
+    Merge {
+        #[arg(required = true, value_parser = tags::parse_tag)]
+        tags: Vec<String>,
+        /// Tag replacing them, created when no log has it yet
+        #[arg(long, value_parser = tags::parse_tag)]
+        into: String,
+    },
+
+    /// Remove a tag from every log having it, the logs are kept
+    Delete {
+        #[arg(value_parser = tags::parse_tag)]
+        tag: String,
+        /// Remove without asking for confirmation
         #[arg(long, short)]
-        end_date: Option<String>,
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum QuLogConfigCommand {
+    /// Print the value of a key
+    Get {
         #[arg(value_enum)]
-        date_range: Option<QuLogCommandDateRange>,
-        #[arg(long)]
-        to: Option<String>,
+        key: config::QuLogConfigKey,
+    },
+
+    /// Set a key, an empty value removes it
+    Set {
+        #[arg(value_enum)]
+        key: config::QuLogConfigKey,
+        value: String,
     },
+
+    /// Print every key of the config file
+    List,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, ValueEnum)]
-enum QuLogCommandDateRange {
-    Today,
-    ThisWeek,
-    ThisMonth,
-    ThisYear,
+fn parse_date_format(value: &str) -> Result<String, String> {
+    config::validate_date_format(value).map(|_| value.to_string())
 }
 
-impl QuLogCommandDateRange {
-    fn date_times(&self) -> (DateTime<Local>, DateTime<Local>) {
-        match self {
-            QuLogCommandDateRange::Today => (
-                Local::now().with_time(NaiveTime::MIN).unwrap(),
-                Local::now()
-                    .checked_add_days(Days::new(1))
-                    .unwrap()
-                    .with_time(NaiveTime::MIN)
-                    .unwrap(),
-            ),
-            QuLogCommandDateRange::ThisWeek => {
-                let days_since = Local::now().weekday().days_since(chrono::Weekday::Mon);
-                let start_of_week = Local::now()
-                    .checked_sub_days(chrono::Days::new(days_since as u64))
-                    .unwrap()
-                    .with_time(NaiveTime::MIN)
-                    .unwrap();
+#[derive(Debug, Subcommand)]
+enum QuLogDbCommand {
+    /// Apply pending schema migrations
+    Migrate {
+        /// Only list the migrations that would be applied
+        #[arg(long)]
+        dry_run: bool,
+    },
 
-                (start_of_week, Local::now())
-            }
-            QuLogCommandDateRange::ThisMonth => {
-                let days_since = Local::now().day();
-                let start_of_month = Local::now()
-                    .checked_sub_days(chrono::Days::new(days_since as u64))
-                    .unwrap()
-                    .with_time(NaiveTime::MIN)
-                    .unwrap();
-                (start_of_month, Local::now())
-            }
-            QuLogCommandDateRange::ThisYear => {
-                let current_year = Local::now().year();
-                let start_of_year = NaiveDate::from_ymd_opt(current_year - 1, 1, 1)
-                    .unwrap()
-                    .and_time(NaiveTime::MIN)
-                    .and_local_timezone(Local)
-                    .unwrap();
+    /// Show applied and pending schema migrations
+    Status,
 
-                (start_of_year, Local::now())
-            }
-        }
-    }
+    /// Show which database file is used
+    Info,
 }
 
-struct QuLogCommandParser {
-    start_date: Option<DateTime<Local>>,
-    end_date: Option<DateTime<Local>>,
-    tags: QuLogTags,
+#[derive(Debug, Args)]
+struct QuLogFilterArgs {
+    #[arg(long)]
+    tags: Option<String>,
+    #[arg(long, value_enum, default_value_t)]
+    tag_match: QuLogTagMatch,
+    /// Logs with this level, or >=, >, <= and < it, like --level=>=warn
+    #[arg(long)]
+    level: Option<level::QuLogLevelFilter>,
+    /// Logs from this source
+    #[arg(long)]
+    source: Option<String>,
+    /// Logs whose field is key=value, every one given must match
+    #[arg(long = "field", value_parser = level::parse_field)]
+    fields: Vec<(String, String)>,
+    /// Logs from this date on, like 2025-04-02, "2025-04-02 10:30", yesterday, last monday,
+    /// "3 days ago", 2025-W14 or an ISO 8601 date with offset
+    #[arg(long, short)]
+    start_date: Option<String>,
+    /// Logs until this date, a whole day like 2025-04-02 includes all of it
+    #[arg(long, short)]
+    end_date: Option<String>,
+    #[arg(value_enum)]
+    date_range: Option<ranges::QuLogDateRange>,
+    /// Number of days of last-n-days
+    #[arg(required_if_eq("date_range", "last-n-days"))]
+    days: Option<u32>,
 }
 
-impl QuLogCommandParser {
-    fn parse(
-        tags: Option<String>,
-        start_date: Option<String>,
-        end_date: Option<String>,
-        date_range: Option<QuLogCommandDateRange>,
-    ) -> Self {
-        fn parse_local_date_from(value: Option<String>) -> Option<DateTime<Local>> {
-            value
-                .and_then(|s| NaiveDateTime::parse_from_str(s.as_str(), "%Y-%m-%d %H:%M:%S").ok())
-                .map(|f| f.and_local_timezone(Local).unwrap())
+#[derive(Debug, Args)]
+struct QuLogPageArgs {
+    /// Show at most this many logs
+    #[arg(long)]
+    limit: Option<u32>,
+    /// Skip this many logs first
+    #[arg(long, default_value_t = 0)]
+    offset: u32,
+    /// Oldest or newest logs first, --limit and --offset count in this order
+    #[arg(long, value_enum, default_value_t)]
+    sort: QuLogSort,
+    /// Print the selected logs the other way around, like the newest 10 oldest first
+    #[arg(long)]
+    reverse: bool,
+}
+
+impl QuLogPageArgs {
+    fn apply(&self, filter: QuLogFilter) -> QuLogFilter {
+        QuLogFilter {
+            sort: self.sort,
+            limit: self.limit,
+            offset: self.offset,
+            ..filter
         }
+    }
+}
 
-        if date_range.is_some() && (start_date.is_some() || end_date.is_some()) {
-            panic!("Date filtering can be done by date_range or start_date and end_date.")
+impl QuLogFilterArgs {
+    /// Builds the filter, relative dates and ranges are read from `clock`.
+    fn parse(self, clock: &dyn Clock, week_start: Weekday) -> Result<QuLogFilter, QuLogError> {
+        if self.date_range.is_some() && (self.start_date.is_some() || self.end_date.is_some()) {
+            return Err(QuLogError::Validation(
+                "Date filtering can be done by date_range or start_date and end_date.".to_string(),
+            ));
         }
 
-        let range: (Option<DateTime<Local>>, Option<DateTime<Local>>) = match date_range {
+        let range: (Option<DateTime<Local>>, Option<DateTime<Local>>) = match self.date_range {
             Some(date_range) => {
-                let range = date_range.date_times();
-                (Some(range.0), Some(range.1))
+                let span = date_range
+                    .span(self.days, clock, week_start)
+                    .map_err(QuLogError::Validation)?;
+                (Some(span.start), Some(span.end))
             }
             None => (
-                parse_local_date_from(start_date),
-                parse_local_date_from(end_date),
+                self.start_date
+                    .map(|date| dates::parse_date(&date, clock).map(|span| span.start))
+                    .transpose()
+                    .map_err(QuLogError::Parse)?,
+                self.end_date
+                    .map(|date| dates::parse_date(&date, clock).map(|span| span.end))
+                    .transpose()
+                    .map_err(QuLogError::Parse)?,
             ),
         };
 
-        let tags = match tags {
+        let tags = match self.tags {
             Some(tags) => QuLogTags::from(tags),
             None => QuLogTags::empty(),
         };
 
-        QuLogCommandParser {
+        Ok(QuLogFilter {
+            tags,
+            tag_match: self.tag_match,
+            levels: self.level.map(|level| level.0).unwrap_or_default(),
+            source: self.source,
+            fields: self.fields,
             start_date: range.0,
             end_date: range.1,
-            tags,
+            ..Default::default()
+        })
+    }
+}
+
+/// How long logs are kept by `prune`, written like `90d`, `12w`, `6m` or `1y`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum QuLogRetention {
+    Days(u64),
+    Weeks(u64),
+    Months(u32),
+    Years(u32),
+}
+
+impl FromStr for QuLogRetention {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let invalid =
+            || format!("invalid period {value:?}, expected something like 90d, 12w, 6m or 1y");
+
+        let unit = value.chars().last().ok_or_else(invalid)?;
+        let amount = &value[..value.len() - unit.len_utf8()];
+
+        match unit {
+            'd' => amount.parse().map(QuLogRetention::Days),
+            'w' => amount.parse().map(QuLogRetention::Weeks),
+            'm' => amount.parse().map(QuLogRetention::Months),
+            'y' => amount.parse().map(QuLogRetention::Years),
+            _ => return Err(invalid()),
+        }
+        .map_err(|_| invalid())
+    }
+}
+
+impl QuLogRetention {
+    /// The moment before which logs fall out of the retention period.
+    fn cutoff(&self, clock: &dyn Clock) -> Result<DateTime<Local>, QuLogError> {
+        let now = clock.now();
+        match *self {
+            QuLogRetention::Days(days) => now.checked_sub_days(Days::new(days)),
+            QuLogRetention::Weeks(weeks) => weeks
+                .checked_mul(7)
+                .and_then(|days| now.checked_sub_days(Days::new(days))),
+            QuLogRetention::Months(months) => now.checked_sub_months(Months::new(months)),
+            QuLogRetention::Years(years) => years
+                .checked_mul(12)
+                .and_then(|months| now.checked_sub_months(Months::new(months))),
         }
+        .ok_or(QuLogError::Validation(
+            "Retention period is out of range".to_string(),
+        ))
     }
 }
 
-pub async fn run_qulog() {
-    let args = LogCreateCli::parse();
+/// Runs qulog with the arguments of the process.
+pub async fn run_qulog() -> Result<(), QuLogError> {
+    run_qulog_with(LogCreateCli::parse()).await
+}
+
+/// Runs qulog with already parsed arguments, failures are returned instead of exiting
+/// so it can be embedded. [`QuLogError::exit_code`] is the code to exit with.
+pub async fn run_qulog_with(args: LogCreateCli) -> Result<(), QuLogError> {
+    run_qulog_with_clock(args, &SystemClock).await
+}
+
+async fn run_qulog_with_clock(args: LogCreateCli, clock: &dyn Clock) -> Result<(), QuLogError> {
+    let env = |key: &str| std::env::var(key).ok();
+    let config_path = config::config_file_path(&env);
 
-    let config = DBConfig { in_memory: false };
-    let pool = match connect_to_db(&config).await {
-        Ok(pool) => pool,
-        Err(err) => panic!("{:?}", err),
+    // Before the config is loaded, so it can be fixed when it can't be
+    let command = match args.command {
+        QuLogCommand::Config { command } => {
+            return run_config_command(command, config_path.as_deref());
+        }
+        command => command,
     };
 
-    create_log_table_if_not_exists(&pool)
-        .await
-        .expect("Unable to create log data base");
+    let settings = match &config_path {
+        Some(path) => config::QuLogConfig::load(path)?,
+        None => config::QuLogConfig::default(),
+    };
+
+    let display = dates::QuLogDateDisplay {
+        format: args
+            .date_format
+            .or(settings.date_format.clone())
+            .unwrap_or(config::DEFAULT_DATE_FORMAT.to_string()),
+        tz: args.tz.or(settings.time_zone).unwrap_or_default(),
+    };
+
+    let week_start = args
+        .week_start
+        .or(settings.week_start)
+        .unwrap_or(Weekday::Mon);
+
+    let config = DBConfig::resolve(args.db, &settings, config_path.as_deref());
+    let store = config
+        .open(!matches!(command, QuLogCommand::Db { .. }))
+        .await?;
+
+    match command {
+        QuLogCommand::Log {
+            text,
+            tags,
+            edit,
+            level,
+            source,
+            fields,
+            attachments,
+        } => {
+            // Read first so a missing file doesn't leave a log behind
+            let files = attachments
+                .iter()
+                .map(|path| attachment::QuLogAttachmentFile::read(path))
+                .collect::<Result<Vec<_>, _>>()?;
 
-    match args.command {
-        QuLogCommand::Log { text, tags } => {
+            let text = input::read_log_text(text, edit, std::io::stdin(), &env)?;
             let tags: QuLogTags = match tags {
                 Some(tags) => QuLogTags::from(tags),
 
-                None => QuLogTags::empty(),
+                None => QuLogTags(settings.default_tags.unwrap_or_default()),
             };
 
             let log = QuLog {
+                id: None,
                 text,
                 tags,
-                create_date: Local::now(),
+                level,
+                source,
+                fields: fields.into_iter().collect(),
+                attachments: Vec::new(),
+                create_date: clock.now().fixed_offset(),
+                updated_date: None,
             };
 
-            match create_log(&log, &pool).await {
-                Ok(_) => (),
-                Err(err) => println!("Unable to save log {:?}", err),
+            if files.is_empty() {
+                store.insert(&log).await?;
+            } else {
+                let pool = &sqlite(store.as_ref(), "log --attach")?.pool;
+                attachment::create_log_with_attachments(pool, &log, &files).await?;
             }
         }
 
-        QuLogCommand::Show {
-            tags,
-            start_date,
-            end_date,
-            date_range,
-        } => {
-            let parameters = QuLogCommandParser::parse(tags, start_date, end_date, date_range);
-
-            let logs = fetch_logs(
-                &pool,
-                Some(parameters.tags),
-                parameters.start_date,
-                parameters.end_date,
-            )
-            .await
-            .expect("Unable to fetch logs");
+        QuLogCommand::Show { filter, page } => {
+            let filter = page.apply(filter.parse(clock, week_start)?);
 
-            if logs.is_empty() {
-                println!("No record is found");
-                return;
-            }
+            // Printed as they are read, unless they have to be turned around first
+            let mut logs = if page.reverse {
+                let mut logs = store.query(&filter).await?;
+                logs.reverse();
+                stream::iter(logs.into_iter().map(Ok)).boxed()
+            } else {
+                store.fetch(&filter)
+            };
 
-            for log in logs {
+            let mut found = false;
+            while let Some(log) = logs.try_next().await? {
+                found = true;
+                let edited = if log.updated_date.is_some() {
+                    " (edited)"
+                } else {
+                    ""
+                };
+                let suffix = format!("{}{}", format_fields(&log.fields), edited);
                 println!(
-                    "-> {} : {} [{}]",
-                    log.create_date.format("%Y-%m-%d %H:%M:%S"),
-                    log.text,
-                    log.tags.0.join("-")
+                    "{}",
+                    format_entry(&entry_prefix(&log, &display), &log.text, &log.tags, &suffix)
                 );
+                for attachment in &log.attachments {
+                    println!(
+                        "   + [{}] {} ({})",
+                        attachment.id,
+                        attachment.name,
+                        attachment::format_size(attachment.size)
+                    );
+                }
+            }
+
+            if !found {
+                println!("No record is found");
             }
         }
 
         QuLogCommand::Export {
-            tags,
-            start_date,
-            end_date,
-            date_range,
+            filter,
             to,
+            css,
+            template,
+            format,
         } => {
-            let parameters = QuLogCommandParser::parse(tags, start_date, end_date, date_range);
-
-            let logs = fetch_logs(
-                &pool,
-                Some(parameters.tags),
-                parameters.start_date,
-                parameters.end_date,
-            )
-            .await
-            .expect("Unable to fetch logs");
+            let logs = store.query(&filter.parse(clock, week_start)?).await?;
 
             if logs.is_empty() {
                 println!("No record is found");
-                return;
-            }
-
-            let style =
-                std::fs::read_to_string("resources/table-css.css").unwrap_or("".to_string());
-
-            let mut table = String::from(
-                r#"<table><tr>
-                <th style="width:20%">Date</th>
-                <th style="width:60%">Log</th>
-                <th style="width:20%">Tags</th>
-                </tr>"#,
-            );
-
-            for log in logs {
-                let row = format!(
-                    "<tr><td>{date}</td><td>{log}</td><td>{tags}</td>",
-                    date = log.create_date.format("%Y-%m-%d %H:%M:%S"),
-                    log = log.text,
-                    tags = log.tags.0.join("-")
-                );
-                table.push_str(row.as_str());
+                return Ok(());
             }
 
-            table.push_str("</table>");
+            let css_path = css
+                .or(settings
+                    .css_path
+                    .map(|path| config::expand_home(&path, &env)))
+                .unwrap_or(PathBuf::from(config::DEFAULT_CSS_PATH));
+            let style = std::fs::read_to_string(css_path).unwrap_or("".to_string());
+
+            let template = match template.or(settings
+                .template_path
+                .map(|path| config::expand_home(&path, &env)))
+            {
+                Some(path) => std::fs::read_to_string(&path).map_err(|err| {
+                    QuLogError::io(format!("Unable to read template {}", path.display()), err)
+                })?,
+                None => export::DEFAULT_HTML_TEMPLATE.to_string(),
+            };
 
-            let html = format!(
-                "<html><head><style>{style}</style></head><body>{table}</body></html>",
-                style = style,
-                table = table
-            );
+            let exporter = format.exporter(&display, export::QuLogHtmlTheme { template, style });
+            let content = exporter.export(&logs).map_err(QuLogError::Export)?;
 
             let export_path = match to {
                 Some(to) => {
                     if Path::new(&to).is_dir() {
                         to
                     } else {
-                        panic!("Invalid export path")
+                        return Err(QuLogError::Validation(format!(
+                            "Invalid export path {}, it must be an existing directory",
+                            to
+                        )));
                     }
                 }
                 None => {
-                    let path = "./exports";
-                    if !Path::new(path).is_dir() {
-                        std::fs::create_dir(path).expect("Unable to create export path");
+                    let path = settings
+                        .export_dir
+                        .map(|path| config::expand_home(&path, &env))
+                        .unwrap_or(PathBuf::from(config::DEFAULT_EXPORT_DIR));
+                    if !path.is_dir() {
+                        std::fs::create_dir_all(&path).map_err(|err| {
+                            QuLogError::io(format!("Unable to create {}", path.display()), err)
+                        })?;
                     }
-                    path.to_string()
+                    path.display().to_string()
                 }
             };
 
-            let filename = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-            let file = format!("{}/{}.html", export_path, filename);
+            let filename = clock.now().format("%Y-%m-%d %H:%M:%S").to_string();
+            let file = format!("{}/{}.{}", export_path, filename, exporter.extension());
 
-            std::fs::write(file, html).expect("Unable to write export file");
+            std::fs::write(&file, content)
+                .map_err(|err| QuLogError::io(format!("Unable to write {}", file), err))?;
+
+            if format == export::QuLogExportFormat::Html {
+                if let Some(store) = store.as_sqlite() {
+                    export_attachments(&store.pool, &logs, Path::new(&export_path)).await?;
+                }
+            }
         }
-    }
-}
 
-async fn create_log_table_if_not_exists(pool: &SqlitePool) -> Result<(), sqlx::Error> {
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS qu_log(
-            id  INTEGER PRIMARY KEY AUTOINCREMENT,
-            text TEXT NOT NULL,
-            tags TEXT DEFAULT '',
-            create_date DATETIME NOT NULL
-        )
-    "#,
-    )
-    .execute(pool)
-    .await?;
-    return Ok(());
-}
+        QuLogCommand::Attachment {
+            command: QuLogAttachmentCommand::Get { id, output, force },
+        } => {
+            let pool = &sqlite(store.as_ref(), "attachment")?.pool;
+            let (attachment, data) = match attachment::fetch_attachment(pool, id).await? {
+                Some(attachment) => attachment,
+                None => {
+                    return Err(QuLogError::Validation(format!(
+                        "No attachment with id {} is found",
+                        id
+                    )))
+                }
+            };
 
-async fn create_log(model: &QuLog, pool: &SqlitePool) -> Result<(), sqlx::Error> {
-    let db_model = QuLogDBO::from(model);
-    sqlx::query("INSERT INTO qu_log (text, create_date, tags) VALUES ($1, $2, $3)")
-        .bind(db_model.text)
-        .bind(db_model.create_date)
-        .bind(db_model.tags)
-        .execute(pool)
-        .await?;
-    Ok(())
-}
+            if output.as_deref() == Some(Path::new("-")) {
+                return std::io::stdout()
+                    .write_all(&data)
+                    .map_err(|err| QuLogError::io("Unable to write to stdout", err));
+            }
 
-async fn connect_to_db(config: &DBConfig) -> Result<SqlitePool, sqlx::Error> {
-    if config.in_memory {
-        return SqlitePool::connect("sqlite::memory:").await;
+            let path = match output {
+                Some(path) if path.is_dir() => path.join(attachment.file_name()),
+                Some(path) => path,
+                None => PathBuf::from(attachment.file_name()),
+            };
+            if path.exists() && !force {
+                return Err(QuLogError::Validation(format!(
+                    "{} already exists, pass --force to overwrite it",
+                    path.display()
+                )));
+            }
+            std::fs::write(&path, data).map_err(|err| {
+                QuLogError::io(format!("Unable to write {}", path.display()), err)
+            })?;
+            println!("Written {}", path.display());
+        }
+
+        QuLogCommand::Import { file, format } => {
+            let format = match format.or(import::QuLogImportFormat::from_path(&file)) {
+                Some(format) => format,
+                None => {
+                    return Err(QuLogError::Validation(format!(
+                        "Unable to guess the format of {}, pass --format",
+                        file.display()
+                    )))
+                }
+            };
+            let content = std::fs::read_to_string(&file)
+                .map_err(|err| QuLogError::io(format!("Unable to read {}", file.display()), err))?;
+            let rows = format.parse(&content).map_err(QuLogError::Parse)?;
+
+            let report = import::import_logs(&sqlite(store.as_ref(), "import")?.pool, rows).await?;
+
+            for error in &report.errors {
+                eprintln!("Skipped {}", error);
+            }
+            println!(
+                "Imported {} logs, skipped {} duplicates and {} invalid rows",
+                report.imported,
+                report.duplicates,
+                report.errors.len()
+            );
+        }
+
+        QuLogCommand::Edit {
+            id,
+            text,
+            add_tags,
+            remove_tags,
+        } => {
+            let update = QuLogUpdate {
+                text,
+                add_tags: QuLogTags::from(add_tags.join(",")),
+                remove_tags: QuLogTags::from(remove_tags.join(",")),
+            };
+
+            match store.update_at(id, &update, clock.now().to_utc()).await? {
+                Some(log) => {
+                    let prefix = entry_prefix(&log, &display);
+                    let fields = format_fields(&log.fields);
+                    println!("{}", format_entry(&prefix, &log.text, &log.tags, &fields));
+                }
+                None => println!("No log with id {} is found", id),
+            }
+        }
+
+        QuLogCommand::History { id } => {
+            let edits = fetch_log_edits(&sqlite(store.as_ref(), "history")?.pool, id).await?;
+
+            if edits.is_empty() {
+                println!("No edit is found for log {}", id);
+                return Ok(());
+            }
+
+            for edit in edits {
+                println!("-> {}", display.format(edit.edit_date));
+                let old_tags = QuLogTags::from(edit.old_tags);
+                println!("{}", format_entry("   - ", &edit.old_text, &old_tags, ""));
+                let new_tags = QuLogTags::from(edit.new_tags);
+                println!("{}", format_entry("   + ", &edit.new_text, &new_tags, ""));
+            }
+        }
+
+        QuLogCommand::Tags { command } => {
+            run_tags_command(command, &sqlite(store.as_ref(), "tags")?.pool, clock).await?
+        }
+
+        QuLogCommand::Delete { filter, yes } => {
+            delete_with_preview(store.as_ref(), &filter.parse(clock, week_start)?, yes).await?;
+        }
+
+        QuLogCommand::Prune { older_than, yes } => {
+            let filter = QuLogFilter {
+                end_date: Some(older_than.cutoff(clock)?),
+                ..Default::default()
+            };
+            delete_with_preview(store.as_ref(), &filter, yes).await?;
+        }
+
+        QuLogCommand::Search { query, limit } => {
+            let highlight = if std::io::stdout().is_terminal() {
+                search::TERMINAL_HIGHLIGHT
+            } else {
+                search::NO_HIGHLIGHT
+            };
+
+            let hits = search::search_logs(
+                &sqlite(store.as_ref(), "search")?.pool,
+                &query,
+                limit,
+                highlight,
+            )
+            .await?;
+
+            if hits.is_empty() {
+                println!("No record is found");
+                return Ok(());
+            }
+
+            for hit in hits {
+                let prefix = entry_prefix(&hit.log, &display);
+                let fields = format_fields(&hit.log.fields);
+                println!(
+                    "{}",
+                    format_entry(&prefix, &hit.snippet, &hit.log.tags, &fields)
+                );
+            }
+        }
+
+        QuLogCommand::Stats {
+            filter,
+            by,
+            top,
+            json,
+        } => {
+            let logs = store.query(&filter.parse(clock, week_start)?).await?;
+            let stats = stats::QuLogStats::new(&logs, &display, by, week_start, top);
+
+            if json {
+                let json = serde_json::to_string_pretty(&stats).map_err(|err| {
+                    QuLogError::Validation(format!("Unable to serialize stats: {}", err))
+                })?;
+                println!("{}", json);
+            } else {
+                println!("{}", stats::render(&stats, by, week_start));
+            }
+        }
+
+        QuLogCommand::Tui => tui::run(store.as_ref(), clock, week_start, display).await?,
+
+        QuLogCommand::Db { command } => {
+            run_db_command(command, &sqlite(store.as_ref(), "db")?.pool, &config).await?
+        }
+        QuLogCommand::Config { .. } => unreachable!("config commands run before connecting"),
+    }
+    Ok(())
+}
+
+/// Works on the file as written rather than `QuLogConfig`, so a config qulog
+/// refuses to load can still be read and repaired.
+fn run_config_command(
+    command: QuLogConfigCommand,
+    config_path: Option<&Path>,
+) -> Result<(), QuLogError> {
+    let config_path = config_path.ok_or(QuLogError::Validation(
+        "Unable to find the config directory, HOME is not set".to_string(),
+    ))?;
+    let mut settings = config::QuLogRawConfig::load(config_path)?;
+
+    match command {
+        QuLogConfigCommand::Get { key } => {
+            if let Some(value) = settings.get(key) {
+                println!("{}", value);
+            }
+        }
+
+        QuLogConfigCommand::Set { key, value } => {
+            settings.set(key, &value).map_err(QuLogError::Validation)?;
+            settings.save(config_path)?;
+        }
+
+        QuLogConfigCommand::List => {
+            println!("# {}", config_path.display());
+            for key in config::QuLogConfigKey::value_variants() {
+                let line = match settings.get(*key) {
+                    Some(value) => format!("{} = {}", key.name(), value),
+                    None => format!("{} =", key.name()),
+                };
+                match settings.check(*key) {
+                    Ok(()) => println!("{}", line),
+                    Err(err) => println!("{}  # invalid: {}", line, err),
+                }
+            }
+            for (name, value) in settings.unknown_keys() {
+                println!(
+                    "{} = {}  # unknown key, remove it from the file",
+                    name, value
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A log as `show` prints it: `prefix`, the text, its tags and `suffix`.
+/// Lines of multi-line texts after the first are lined up under it.
+fn format_entry(prefix: &str, text: &str, tags: &QuLogTags, suffix: &str) -> String {
+    let indent = " ".repeat(prefix.chars().count());
+    let mut lines = text.lines();
+    let mut entry = format!("{}{}", prefix, lines.next().unwrap_or_default());
+    for line in lines {
+        entry.push('\n');
+        if !line.is_empty() {
+            entry.push_str(&indent);
+            entry.push_str(line);
+        }
+    }
+    format!("{} [{}]{}", entry, tags.0.join("-"), suffix)
+}
+
+/// `-> [id] date : ` before the text of `log`, with its level unless it's `info`
+/// and its source when it has one.
+fn entry_prefix(log: &QuLog, display: &dates::QuLogDateDisplay) -> String {
+    let mut prefix = format!(
+        "-> [{}] {}",
+        log.id.unwrap_or_default(),
+        display.format(log.create_date)
+    );
+    if log.level != QuLogLevel::Info {
+        prefix.push(' ');
+        prefix.push_str(&log.level.as_str().to_uppercase());
+    }
+    if let Some(source) = &log.source {
+        prefix.push_str(&format!(" ({})", source));
     }
+    prefix.push_str(" : ");
+    prefix
+}
+
+/// ` key=value` for every field, printed after the tags.
+fn format_fields(fields: &BTreeMap<String, String>) -> String {
+    fields
+        .iter()
+        .map(|(key, value)| format!(" {}={}", key, value))
+        .collect()
+}
+
+/// The SQLite database behind `store`, for `command`s that only work on one.
+fn sqlite<'a>(store: &'a dyn LogStore, command: &str) -> Result<&'a QuLogStore, QuLogError> {
+    store.as_sqlite().ok_or_else(|| {
+        QuLogError::Validation(format!(
+            "qulog {} needs a SQLite database, not a .jsonl file",
+            command
+        ))
+    })
+}
 
-    let dbname = "logs.db";
+/// Writes the attachments of `logs` to the folder the html export links them from.
+async fn export_attachments(
+    pool: &SqlitePool,
+    logs: &[QuLog],
+    export_path: &Path,
+) -> Result<(), QuLogError> {
+    let dir = export_path.join(attachment::EXPORT_DIR);
+    for listed in logs.iter().flat_map(|log| &log.attachments) {
+        let path = dir.join(listed.file_name());
+        if path.exists() {
+            continue;
+        }
+        if let Some((_, data)) = attachment::fetch_attachment(pool, listed.id).await? {
+            std::fs::create_dir_all(&dir).map_err(|err| {
+                QuLogError::io(format!("Unable to create {}", dir.display()), err)
+            })?;
+            std::fs::write(&path, data).map_err(|err| {
+                QuLogError::io(format!("Unable to write {}", path.display()), err)
+            })?;
+        }
+    }
+    Ok(())
+}
 
-    let db_file_path = std::env::current_dir()?.join(dbname);
-    if !db_file_path.exists() {
-        std::fs::File::create(db_file_path).expect("Unable to create db file");
+/// Deletes the logs matching `filter` once the user confirmed the number of logs to delete.
+async fn delete_with_preview(
+    store: &dyn LogStore,
+    filter: &QuLogFilter,
+    yes: bool,
+) -> Result<(), QuLogError> {
+    let count = store.count(filter).await?;
+
+    if count == 0 {
+        println!("No record is found");
+        return Ok(());
     }
 
-    let db_path = format!("sqlite://{}", dbname);
-    return SqlitePool::connect(&db_path).await;
+    println!("{} log(s) will be deleted", count);
+
+    if !yes && !confirm("Delete them?")? {
+        println!("Nothing is deleted");
+        return Ok(());
+    }
+
+    let deleted = store.delete(filter).await?;
+    println!("{} log(s) deleted", deleted);
+    Ok(())
+}
+
+/// Asks a yes/no question on the terminal, anything but `y`/`yes` is a no.
+fn confirm(question: &str) -> Result<bool, QuLogError> {
+    if !std::io::stdin().is_terminal() {
+        println!("Not running in a terminal, pass --yes to confirm");
+        return Ok(false);
+    }
+
+    print!("{} [y/N] ", question);
+    std::io::stdout()
+        .flush()
+        .map_err(|err| QuLogError::io("Unable to write to the terminal", err))?;
+
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .map_err(|err| QuLogError::io("Unable to read the answer", err))?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
 }
 
-async fn fetch_logs(
+async fn run_tags_command(
+    command: QuLogTagsCommand,
     pool: &SqlitePool,
-    tags: Option<QuLogTags>,
-    start_date: Option<DateTime<Local>>,
-    end_date: Option<DateTime<Local>>,
-) -> Result<Vec<QuLog>, sqlx::Error> {
-    let tags = match tags {
-        Some(tags) if !tags.0.is_empty() => tags.into(),
-        _ => "%".to_string(),
+    clock: &dyn Clock,
+) -> Result<(), QuLogError> {
+    let now = clock.now().to_utc();
+    match command {
+        QuLogTagsCommand::List => {
+            let usages = tags::list_tags(pool).await?;
+            if usages.is_empty() {
+                println!("No tag is found");
+            }
+            let width = usages
+                .iter()
+                .map(|usage| usage.name.chars().count())
+                .max()
+                .unwrap_or(0);
+            for usage in usages {
+                println!("{:width$}  {}", usage.name, usage.count);
+            }
+        }
+
+        QuLogTagsCommand::Rename { old, new } => {
+            let changed = tags::rename_tag(pool, &old, &new, now).await?;
+            println!("{} renamed to {} on {} log(s)", old, new, changed);
+        }
+
+        QuLogTagsCommand::Merge { tags, into } => {
+            let changed = tags::merge_tags(pool, &tags, &into, now).await?;
+            println!(
+                "{} merged into {} on {} log(s)",
+                tags.join(", "),
+                into,
+                changed
+            );
+        }
+
+        QuLogTagsCommand::Delete { tag, yes } => {
+            let count = tags::list_tags(pool)
+                .await?
+                .into_iter()
+                .find(|usage| usage.name == tag)
+                .map(|usage| usage.count)
+                .ok_or_else(|| QuLogError::Validation(format!("No tag named {} is found", tag)))?;
+            println!("{} will be removed from {} log(s)", tag, count);
+
+            if !yes && !confirm("Remove it?")? {
+                println!("Nothing is changed");
+                return Ok(());
+            }
+
+            let changed = tags::delete_tag(pool, &tag, now).await?;
+            println!("{} removed from {} log(s)", tag, changed);
+        }
+    }
+    Ok(())
+}
+
+async fn run_db_command(
+    command: QuLogDbCommand,
+    pool: &SqlitePool,
+    config: &DBConfig,
+) -> Result<(), QuLogError> {
+    match command {
+        QuLogDbCommand::Info => {
+            let applied = migrations::applied_migrations(pool).await?;
+            let version = applied.iter().map(|m| m.version).max().unwrap_or(0);
+
+            let path = std::fs::canonicalize(&config.path).unwrap_or(config.path.clone());
+            println!("Database: {}", path.display());
+            println!("Resolved from: {}", config.path_source);
+            println!("Schema version: {}", version);
+        }
+
+        QuLogDbCommand::Migrate { dry_run: true } => {
+            let pending = migrations::pending_migrations(pool).await?;
+
+            if pending.is_empty() {
+                println!("Database is up to date");
+            }
+            for migration in pending {
+                println!("Would apply {:04} {}", migration.version, migration.name);
+            }
+        }
+
+        QuLogDbCommand::Migrate { dry_run: false } => {
+            let applied = migrations::migrate(pool).await?;
+
+            if applied.is_empty() {
+                println!("Database is up to date");
+            }
+            for migration in applied {
+                println!("Applied {:04} {}", migration.version, migration.name);
+            }
+        }
+
+        QuLogDbCommand::Status => {
+            let applied = migrations::applied_migrations(pool).await?;
+
+            let version = applied.iter().map(|m| m.version).max().unwrap_or(0);
+            println!("Schema version: {}", version);
+
+            for migration in migrations::MIGRATIONS {
+                match applied.iter().find(|m| m.version == migration.version) {
+                    Some(applied) => println!(
+                        "[x] {:04} {} (applied {})",
+                        applied.version,
+                        applied.name,
+                        applied.applied_date.format("%Y-%m-%d %H:%M:%S")
+                    ),
+                    None => println!("[ ] {:04} {} (pending)", migration.version, migration.name),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn create_log(model: &QuLog, pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let log_id = insert_log(&mut tx, model).await?;
+    tx.commit().await?;
+    Ok(log_id)
+}
+
+/// Inserts `model` with its tags, `model.id` is ignored.
+async fn insert_log(conn: &mut SqliteConnection, model: &QuLog) -> Result<i64, sqlx::Error> {
+    let db_model = QuLogDBO::from(model);
+
+    let log_id = sqlx::query(
+        r#"
+        INSERT INTO qu_log (text, level, source, fields, create_date, create_offset, updated_date)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+    "#,
+    )
+    .bind(db_model.text)
+    .bind(db_model.level)
+    .bind(db_model.source)
+    .bind(db_model.fields)
+    .bind(db_model.create_date)
+    .bind(db_model.create_offset)
+    .bind(db_model.updated_date)
+    .execute(&mut *conn)
+    .await?
+    .last_insert_rowid();
+
+    attach_tags(conn, log_id, &model.tags).await?;
+
+    Ok(log_id)
+}
+
+/// Applies `update` to the log with `id` and records the previous version in `qu_log_edit`.
+/// Returns the updated log, or `None` when there is no log with `id`.
+async fn update_log(
+    pool: &SqlitePool,
+    id: i64,
+    update: &QuLogUpdate,
+    now: DateTime<Utc>,
+) -> Result<Option<QuLog>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let old = match fetch_log(&mut tx, id).await? {
+        Some(log) => log,
+        None => return Ok(None),
     };
 
-    let far_future = Local.with_ymd_and_hms(3000, 01, 01, 00, 00, 00).unwrap();
-    let far_past = Local.timestamp_micros(0).unwrap();
+    let (text, tags) = update.apply(&old);
+    if text == old.text && tags == old.tags.0 {
+        return Ok(Some(old));
+    }
+
+    sqlx::query("UPDATE qu_log SET text = $1, updated_date = $2 WHERE id = $3")
+        .bind(&text)
+        .bind(now)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
 
-    let start_date = start_date
-        .unwrap_or(far_past)
-        .format("%Y-%m-%dT%H:%M:%S")
-        .to_string();
+    for tag in old.tags.0.iter().filter(|tag| !tags.contains(tag)) {
+        sqlx::query(
+            "DELETE FROM qu_log_tag WHERE log_id = $1 AND tag_id IN (SELECT id FROM tag WHERE name = $2)",
+        )
+        .bind(id)
+        .bind(tag)
+        .execute(&mut *tx)
+        .await?;
+    }
+    attach_tags(&mut tx, id, &QuLogTags(tags.clone())).await?;
+    delete_unused_tags(&mut tx).await?;
+    insert_edit(&mut tx, &old, &text, &tags, now).await?;
 
-    let end_date = end_date
-        .unwrap_or(far_future)
-        .format("%Y-%m-%dT%H:%M:%S")
-        .to_string();
+    let updated = fetch_log(&mut tx, id).await?;
+    tx.commit().await?;
+    Ok(updated)
+}
 
-    let logs = sqlx::query_as::<Sqlite, QuLogDBO>(
+/// Records in `qu_log_edit` that `old` became `text` and `tags`.
+async fn insert_edit(
+    conn: &mut SqliteConnection,
+    old: &QuLog,
+    text: &str,
+    tags: &[String],
+    now: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
         r#"
-    SELECT * FROM qu_log WHERE tags LIKE ($1) 
-    AND create_date >= ($2) AND create_date <= ($3)
+        INSERT INTO qu_log_edit (log_id, old_text, new_text, old_tags, new_tags, edit_date)
+        VALUES ($1, $2, $3, $4, $5, $6)
     "#,
     )
-    .bind(tags)
-    .bind(start_date)
-    .bind(end_date)
+    .bind(old.id)
+    .bind(&old.text)
+    .bind(text)
+    .bind(old.tags.0.join(","))
+    .bind(tags.join(","))
+    .bind(now)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn fetch_log(conn: &mut SqliteConnection, id: i64) -> Result<Option<QuLog>, sqlx::Error> {
+    let log = sqlx::query_as::<Sqlite, QuLogDBO>(&format!(
+        "SELECT {QU_LOG_COLUMNS} FROM qu_log WHERE qu_log.id = $1"
+    ))
+    .bind(id)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(log.map(|log| log.into()))
+}
+
+/// Edits of the log with `id`, oldest first.
+async fn fetch_log_edits(pool: &SqlitePool, id: i64) -> Result<Vec<QuLogEditDBO>, sqlx::Error> {
+    sqlx::query_as::<Sqlite, QuLogEditDBO>(
+        r#"
+        SELECT old_text, new_text, old_tags, new_tags, edit_date FROM qu_log_edit
+        WHERE log_id = $1 ORDER BY id
+    "#,
+    )
+    .bind(id)
     .fetch_all(pool)
-    .await?
-    .into_iter()
-    .map(|m| m.into())
-    .collect::<Vec<QuLog>>();
+    .await
+}
+
+async fn attach_tags(
+    conn: &mut SqliteConnection,
+    log_id: i64,
+    tags: &QuLogTags,
+) -> Result<(), sqlx::Error> {
+    for tag in &tags.0 {
+        sqlx::query("INSERT OR IGNORE INTO tag (name) VALUES ($1)")
+            .bind(tag)
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query(
+            "INSERT OR IGNORE INTO qu_log_tag (log_id, tag_id) SELECT $1, id FROM tag WHERE name = $2",
+        )
+        .bind(log_id)
+        .bind(tag)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+async fn connect_to_db(config: &DBConfig) -> Result<SqlitePool, sqlx::Error> {
+    if config.in_memory {
+        // Every connection to `sqlite::memory:` opens its own empty database,
+        // so the pool must never hand out a second one.
+        return SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await;
+    }
+
+    if let Some(parent) = config.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    if config.path_source == config::DBPathSource::Default
+        && !config.path.exists()
+        && Path::new("logs.db").is_file()
+    {
+        eprintln!(
+            "Creating a new database at {}, pass --db logs.db to keep using ./logs.db",
+            config.path.display()
+        );
+    }
+
+    let options = SqliteConnectOptions::new()
+        .filename(&config.path)
+        .create_if_missing(true);
+    SqlitePool::connect_with(options).await
+}
+
+/// Columns of `qu_log` mapped by `QuLogDBO`, with tags joined back from `qu_log_tag`
+/// and the attachments from `qu_log_attachment`, without their content.
+const QU_LOG_COLUMNS: &str = r#"
+    qu_log.id, qu_log.text, qu_log.level, qu_log.source, qu_log.fields,
+    qu_log.create_date, qu_log.create_offset, qu_log.updated_date,
+    COALESCE((
+        SELECT GROUP_CONCAT(name, ',') FROM (
+            SELECT tag.name FROM qu_log_tag JOIN tag ON tag.id = qu_log_tag.tag_id
+            WHERE qu_log_tag.log_id = qu_log.id ORDER BY qu_log_tag.rowid
+        )
+    ), '') AS tags,
+    (
+        SELECT json_group_array(json_object('id', id, 'name', name, 'size', size)) FROM (
+            SELECT id, name, size FROM qu_log_attachment
+            WHERE qu_log_attachment.log_id = qu_log.id ORDER BY id
+        )
+    ) AS attachments
+"#;
+
+async fn fetch_logs(pool: &SqlitePool, filter: &QuLogFilter) -> Result<Vec<QuLog>, sqlx::Error> {
+    let mut query = QueryBuilder::<Sqlite>::new(format!("SELECT {QU_LOG_COLUMNS} FROM qu_log"));
+    push_filter(&mut query, filter);
+    push_order(&mut query, filter);
+
+    let logs = query
+        .build_query_as::<QuLogDBO>()
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|m| m.into())
+        .collect::<Vec<QuLog>>();
+
+    Ok(logs)
+}
+
+/// `fetch_logs` handing out logs as rows are read. The query runs in its own task,
+/// a few rows ahead of the stream, so a big result is never held in memory at once.
+fn stream_logs(
+    pool: &SqlitePool,
+    filter: &QuLogFilter,
+) -> BoxStream<'static, Result<QuLog, sqlx::Error>> {
+    let (sender, receiver) = tokio::sync::mpsc::channel(64);
+    let pool = pool.clone();
+    let filter = filter.clone();
+
+    tokio::spawn(async move {
+        let mut query = QueryBuilder::<Sqlite>::new(format!("SELECT {QU_LOG_COLUMNS} FROM qu_log"));
+        push_filter(&mut query, &filter);
+        push_order(&mut query, &filter);
+
+        let mut rows = query.build_query_as::<QuLogDBO>().fetch(&pool);
+        while let Some(row) = rows.next().await {
+            let failed = row.is_err();
+            // Stops once the stream is dropped or the query failed
+            if sender.send(row.map(QuLog::from)).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    stream::unfold(receiver, |mut receiver| async move {
+        let row = receiver.recv().await?;
+        Some((row, receiver))
+    })
+    .boxed()
+}
+
+async fn count_logs(pool: &SqlitePool, filter: &QuLogFilter) -> Result<i64, sqlx::Error> {
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM qu_log");
+    push_selection(&mut query, filter);
+
+    query.build_query_scalar().fetch_one(pool).await
+}
 
-    return Ok(logs);
+/// Deletes the logs matching `filter` along with tags no other log uses.
+async fn delete_logs(pool: &SqlitePool, filter: &QuLogFilter) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let mut query = QueryBuilder::<Sqlite>::new("DELETE FROM qu_log");
+    push_selection(&mut query, filter);
+    let deleted = query.build().execute(&mut *tx).await?.rows_affected();
+    delete_unused_tags(&mut tx).await?;
+
+    tx.commit().await?;
+    Ok(deleted)
+}
+
+async fn delete_unused_tags(conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM tag WHERE id NOT IN (SELECT tag_id FROM qu_log_tag)")
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Appends the `WHERE` clause selecting the `qu_log` rows matching `filter`,
+/// limit and offset included, for statements that can't be ordered like `COUNT` and `DELETE`.
+fn push_selection(query: &mut QueryBuilder<Sqlite>, filter: &QuLogFilter) {
+    if !filter.is_paged() {
+        push_filter(query, filter);
+        return;
+    }
+
+    query.push(" WHERE qu_log.id IN (SELECT qu_log.id FROM qu_log");
+    push_filter(query, filter);
+    push_order(query, filter);
+    query.push(")");
+}
+
+/// Appends `ORDER BY`, then `LIMIT` and `OFFSET` when the filter has them.
+/// Logs written at the same time keep the order they were saved in.
+fn push_order(query: &mut QueryBuilder<Sqlite>, filter: &QuLogFilter) {
+    query.push(match filter.sort {
+        QuLogSort::Asc => " ORDER BY qu_log.create_date ASC, qu_log.id ASC",
+        QuLogSort::Desc => " ORDER BY qu_log.create_date DESC, qu_log.id DESC",
+    });
+
+    if filter.is_paged() {
+        // A negative limit is no limit for SQLite
+        query.push(" LIMIT ");
+        query.push_bind(filter.limit.map_or(-1, i64::from));
+        query.push(" OFFSET ");
+        query.push_bind(i64::from(filter.offset));
+    }
+}
+
+/// Appends the `WHERE` clause selecting the `qu_log` rows matching `filter`.
+fn push_filter(query: &mut QueryBuilder<Sqlite>, filter: &QuLogFilter) {
+    let far_future = Utc.with_ymd_and_hms(3000, 1, 1, 0, 0, 0).unwrap();
+    let far_past = DateTime::UNIX_EPOCH;
+
+    // Bound as UTC dates, encoded like the stored ones so they compare as text
+    let start_date = filter.start_date.map_or(far_past, |date| date.to_utc());
+    let end_date = filter.end_date.map_or(far_future, |date| date.to_utc());
+
+    query.push(" WHERE create_date >= ");
+    query.push_bind(start_date);
+    query.push(" AND create_date < ");
+    query.push_bind(end_date);
+
+    if !filter.ids.is_empty() {
+        query.push(" AND qu_log.id IN (");
+        let mut ids = query.separated(", ");
+        for id in &filter.ids {
+            ids.push_bind(*id);
+        }
+        ids.push_unseparated(")");
+    }
+
+    if !filter.levels.is_empty() {
+        query.push(" AND qu_log.level IN (");
+        let mut levels = query.separated(", ");
+        for level in &filter.levels {
+            levels.push_bind(*level);
+        }
+        levels.push_unseparated(")");
+    }
+
+    if let Some(source) = &filter.source {
+        query.push(" AND qu_log.source = ");
+        query.push_bind(source.clone());
+    }
+
+    for (key, value) in &filter.fields {
+        query.push(" AND EXISTS (SELECT 1 FROM json_each(qu_log.fields) WHERE key = ");
+        query.push_bind(key.clone());
+        query.push(" AND value = ");
+        query.push_bind(value.clone());
+        query.push(")");
+    }
+
+    if !filter.tags.0.is_empty() {
+        push_tags_filter(query, &filter.tags, filter.tag_match);
+    }
 }
 
+fn push_tags_filter(query: &mut QueryBuilder<Sqlite>, tags: &QuLogTags, tag_match: QuLogTagMatch) {
+    let mut distinct_tags = tags.0.clone();
+    distinct_tags.sort();
+    distinct_tags.dedup();
+
+    query.push(
+        r#"
+    AND (
+        SELECT COUNT(DISTINCT tag.name) FROM qu_log_tag JOIN tag ON tag.id = qu_log_tag.tag_id
+        WHERE qu_log_tag.log_id = qu_log.id AND tag.name IN ("#,
+    );
+    let mut names = query.separated(", ");
+    for tag in &distinct_tags {
+        names.push_bind(tag.clone());
+    }
+    names.push_unseparated(") ) >= ");
+
+    match tag_match {
+        QuLogTagMatch::All => query.push_bind(distinct_tags.len() as i64),
+        QuLogTagMatch::Any => query.push_bind(1_i64),
+    };
+}
+
+#[cfg(test)]
 mod tests {
 
-    use chrono::{Days, Months, Timelike};
+    use chrono::{Days, Duration, Months, Timelike};
+
+    use super::clock::FixedClock;
 
     use super::*;
 
     #[test]
     fn test_qulog_model_mapping() {
         let text = "Hello world";
-        let create_date = Local::now().with_nanosecond(0).unwrap();
+        let create_date = Local::now().with_nanosecond(0).unwrap().fixed_offset();
         let tags = vec!["hello".to_string(), "world".to_string()];
         let model = QuLog {
+            id: Some(7),
             text: text.to_string(),
             tags: QuLogTags(tags.clone()),
-            create_date: create_date,
+            level: QuLogLevel::default(),
+            source: None,
+            fields: BTreeMap::new(),
+            attachments: Vec::new(),
+            create_date,
+            updated_date: None,
         };
 
         let db_model = QuLogDBO::from(&model);
         println!("####   {}", db_model.create_date);
+        assert_eq!(db_model.create_date.offset(), &Utc);
+        assert_eq!(
+            db_model.create_offset,
+            create_date.offset().local_minus_utc()
+        );
         let model: QuLog = db_model.into();
 
+        assert_eq!(model.id, Some(7));
         assert_eq!(model.text, text);
         assert_eq!(model.create_date, create_date);
+        assert_eq!(model.create_date.offset(), create_date.offset());
         assert_eq!(model.tags.0, tags);
     }
 
@@ -490,7 +1986,7 @@ mod tests {
         let tags = vec!["hello".to_string(), "world".to_string()];
         insert(&pool, text, &create_date, tags).await.unwrap();
 
-        let all: Vec<QuLog> = fetch_logs(&pool, None, None, None)
+        let all: Vec<QuLog> = fetch_logs(&pool, &QuLogFilter::default())
             .await
             .unwrap()
             .into_iter()
@@ -506,7 +2002,7 @@ mod tests {
         async fn insert_sample_with_date(date: &DateTime<Local>, pool: &SqlitePool) {
             let text = "Hello world";
             let tags = Vec::new();
-            insert(pool, text, &date, tags).await.unwrap();
+            insert(pool, text, date, tags).await.unwrap();
         }
 
         let base = Local::now();
@@ -519,47 +2015,660 @@ mod tests {
 
         insert_sample_with_date(&base.checked_add_months(Months::new(24)).unwrap(), &pool).await;
 
-        let all: Vec<QuLog> = fetch_logs(&pool, None, None, None)
+        let all: Vec<QuLog> = fetch_logs(&pool, &QuLogFilter::default())
             .await
             .unwrap()
             .into_iter()
             .collect();
         assert_eq!(all.len(), 6);
 
-        let this_month: Vec<QuLog> = fetch_logs(
+        let filter = QuLogFilter {
+            start_date: Some(base),
+            end_date: Some(base.checked_add_months(Months::new(1)).unwrap()),
+            ..Default::default()
+        };
+        let this_month: Vec<QuLog> = fetch_logs(&pool, &filter)
+            .await
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(this_month.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_qulog_read_filter_date_across_offsets() {
+        let pool = in_memory_pool().await.unwrap();
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        let new_york = FixedOffset::west_opt(5 * 3600).unwrap();
+
+        // 2024-02-29 23:00 UTC, the 1st of march on the wall clock
+        let tokyo_date = tokyo.with_ymd_and_hms(2024, 3, 1, 8, 0, 0).unwrap();
+        // 2024-03-01 01:00 UTC, still february on the wall clock
+        let new_york_date = new_york.with_ymd_and_hms(2024, 2, 29, 20, 0, 0).unwrap();
+        insert(&pool, "tokyo", &tokyo_date, Vec::new())
+            .await
+            .unwrap();
+        insert(&pool, "new york", &new_york_date, Vec::new())
+            .await
+            .unwrap();
+
+        let filter = QuLogFilter {
+            start_date: Some(Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap().into()),
+            end_date: Some(Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap().into()),
+            ..Default::default()
+        };
+        let logs = fetch_logs(&pool, &filter).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].text, "new york");
+        assert_eq!(logs[0].create_date, new_york_date);
+        assert_eq!(logs[0].create_date.offset(), &new_york);
+
+        let filter = QuLogFilter {
+            end_date: Some((tokyo_date + Duration::seconds(1)).into()),
+            ..Default::default()
+        };
+        let logs = fetch_logs(&pool, &filter).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].text, "tokyo");
+    }
+
+    #[tokio::test]
+    async fn test_qulog_read_filter_end_is_exclusive() {
+        let pool = in_memory_pool().await.unwrap();
+        let midnight = Local.with_ymd_and_hms(2025, 4, 3, 0, 0, 0).unwrap();
+        let before = midnight - Duration::seconds(1);
+        insert(&pool, "late", &before, Vec::new()).await.unwrap();
+        insert(&pool, "next day", &midnight, Vec::new())
+            .await
+            .unwrap();
+
+        let clock = FixedClock(Local.with_ymd_and_hms(2025, 4, 10, 12, 0, 0).unwrap());
+        let texts = |filter: QuLogFilterArgs| {
+            let filter = filter.parse(&clock, Weekday::Mon).unwrap();
+            let pool = pool.clone();
+            async move {
+                fetch_logs(&pool, &filter)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|log| log.text)
+                    .collect::<Vec<_>>()
+            }
+        };
+        let args = |start: &str, end: &str| QuLogFilterArgs {
+            tags: None,
+            tag_match: QuLogTagMatch::All,
+            level: None,
+            source: None,
+            fields: Vec::new(),
+            start_date: Some(start.to_string()),
+            end_date: Some(end.to_string()),
+            date_range: None,
+            days: None,
+        };
+
+        assert_eq!(texts(args("2025-04-02", "2025-04-02")).await, vec!["late"]);
+        assert_eq!(
+            texts(args("2025-04-03", "2025-04-03")).await,
+            vec!["next day"]
+        );
+        // A single instant still includes the logs written at it
+        assert_eq!(
+            texts(args("2025-04-03 00:00", "2025-04-03 00:00")).await,
+            vec!["next day"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_qulog_read_filter_tags() {
+        let pool = in_memory_pool().await.unwrap();
+        let create_date = Local::now();
+
+        insert(&pool, "work", &create_date, vec!["work".to_string()])
+            .await
+            .unwrap();
+        insert(
             &pool,
-            None,
-            Some(base),
-            Some(base.checked_add_months(Months::new(1)).unwrap()),
+            "homework",
+            &create_date,
+            vec!["homework".to_string()],
         )
         .await
-        .unwrap()
-        .into_iter()
-        .collect();
-        assert_eq!(this_month.len(), 3);
+        .unwrap();
+        insert(
+            &pool,
+            "work and rust",
+            &create_date,
+            vec!["work".to_string(), "rust".to_string()],
+        )
+        .await
+        .unwrap();
+        insert(&pool, "rust", &create_date, vec!["rust".to_string()])
+            .await
+            .unwrap();
+
+        async fn texts(pool: &SqlitePool, tags: &str, tag_match: QuLogTagMatch) -> Vec<String> {
+            let filter = QuLogFilter {
+                tags: QuLogTags::from(tags.to_string()),
+                tag_match,
+                ..Default::default()
+            };
+            let mut texts: Vec<String> = fetch_logs(pool, &filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|log| log.text)
+                .collect();
+            texts.sort();
+            texts
+        }
+
+        assert_eq!(
+            texts(&pool, "work", QuLogTagMatch::All).await,
+            vec!["work", "work and rust"]
+        );
+        assert_eq!(
+            texts(&pool, "work,rust", QuLogTagMatch::All).await,
+            vec!["work and rust"]
+        );
+        assert_eq!(
+            texts(&pool, "rust,work", QuLogTagMatch::Any).await,
+            vec!["rust", "work", "work and rust"]
+        );
+        assert_eq!(
+            texts(&pool, "work,work", QuLogTagMatch::All).await,
+            vec!["work", "work and rust"]
+        );
+        assert!(texts(&pool, "wor", QuLogTagMatch::Any).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_qulog_read_filter_level_source_fields() {
+        let pool = in_memory_pool().await.unwrap();
+        migrations::migrate(&pool).await.unwrap();
+
+        let log = |text: &str, level, source: Option<&str>, fields: &[(&str, &str)]| QuLog {
+            level,
+            source: source.map(String::from),
+            fields: fields
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            ..QuLog::new(text, Vec::<String>::new())
+        };
+        for log in [
+            log("booting", QuLogLevel::Debug, Some("web-1"), &[]),
+            log(
+                "deployed",
+                QuLogLevel::Info,
+                Some("deploy.sh"),
+                &[("env", "prod")],
+            ),
+            log(
+                "slow disk",
+                QuLogLevel::Warn,
+                Some("web-1"),
+                &[("env", "prod")],
+            ),
+            log(
+                "tests failed",
+                QuLogLevel::Error,
+                None,
+                &[("env", "staging")],
+            ),
+        ] {
+            create_log(&log, &pool).await.unwrap();
+        }
+
+        let all = fetch_logs(&pool, &QuLogFilter::default()).await.unwrap();
+        assert_eq!(all[1].level, QuLogLevel::Info);
+        assert_eq!(all[1].source.as_deref(), Some("deploy.sh"));
+        assert_eq!(all[1].fields.get("env").map(String::as_str), Some("prod"));
+
+        let texts = |logs: Vec<QuLog>| logs.into_iter().map(|log| log.text).collect::<Vec<_>>();
+        let filters = [
+            (
+                QuLogFilter::builder().min_level(QuLogLevel::Warn).build(),
+                vec!["slow disk", "tests failed"],
+            ),
+            (
+                QuLogFilter::builder().source("web-1").build(),
+                vec!["booting", "slow disk"],
+            ),
+            (
+                QuLogFilter::builder().field("env", "prod").build(),
+                vec!["deployed", "slow disk"],
+            ),
+            (
+                QuLogFilter::builder()
+                    .field("env", "prod")
+                    .levels([QuLogLevel::Info, QuLogLevel::Error])
+                    .build(),
+                vec!["deployed"],
+            ),
+            (
+                QuLogFilter::builder()
+                    .field("env", "prod")
+                    .field("region", "eu")
+                    .build(),
+                vec![],
+            ),
+        ];
+        for (filter, expected) in filters {
+            assert_eq!(texts(fetch_logs(&pool, &filter).await.unwrap()), expected);
+            assert_eq!(texts(filter.select(all.clone())), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_qulog_read_keeps_tag_order() {
+        let pool = in_memory_pool().await.unwrap();
+        let tags = vec!["zeta".to_string(), "alpha".to_string()];
+        insert(&pool, "Hello world", &Local::now(), tags.clone())
+            .await
+            .unwrap();
+
+        let logs = fetch_logs(&pool, &QuLogFilter::default()).await.unwrap();
+        assert_eq!(logs.first().unwrap().tags.0, tags);
+    }
+
+    #[tokio::test]
+    async fn test_qulog_migrate_legacy_db() {
+        let pool = in_memory_pool().await.unwrap();
+        // Schema of databases created before migrations and tag tables existed.
+        sqlx::query(
+            r#"
+            CREATE TABLE qu_log(
+                id  INTEGER PRIMARY KEY AUTOINCREMENT,
+                text TEXT NOT NULL,
+                tags TEXT DEFAULT '',
+                create_date DATETIME NOT NULL
+            )
+        "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        sqlx::query("INSERT INTO qu_log (text, create_date, tags) VALUES ($1, $2, $3)")
+            .bind("old log")
+            .bind(Local::now())
+            .bind("work, rust,,work")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        migrations::migrate(&pool).await.unwrap();
+
+        let logs = fetch_logs(&pool, &QuLogFilter::default()).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].tags.0, vec!["work", "rust"]);
+
+        let inline_tags: String = sqlx::query_scalar("SELECT tags FROM qu_log")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(inline_tags, "");
+    }
+
+    #[tokio::test]
+    async fn test_qulog_count_and_delete() {
+        let pool = in_memory_pool().await.unwrap();
+        let base = Local::now();
+
+        insert(
+            &pool,
+            "old work",
+            &(base - Days::new(10)),
+            vec!["work".to_string()],
+        )
+        .await
+        .unwrap();
+        insert(
+            &pool,
+            "old rust",
+            &(base - Days::new(10)),
+            vec!["rust".to_string()],
+        )
+        .await
+        .unwrap();
+        insert(&pool, "new work", &base, vec!["work".to_string()])
+            .await
+            .unwrap();
+
+        let old_work = QuLogFilter {
+            tags: QuLogTags::from("work".to_string()),
+            end_date: Some(base - Days::new(5)),
+            ..Default::default()
+        };
+        assert_eq!(count_logs(&pool, &old_work).await.unwrap(), 1);
+        assert_eq!(delete_logs(&pool, &old_work).await.unwrap(), 1);
+        assert_eq!(count_logs(&pool, &old_work).await.unwrap(), 0);
+
+        let texts: Vec<String> = fetch_logs(&pool, &QuLogFilter::default())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|log| log.text)
+            .collect();
+        assert_eq!(texts, vec!["old rust", "new work"]);
+
+        let rust = QuLogFilter {
+            tags: QuLogTags::from("rust".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(delete_logs(&pool, &rust).await.unwrap(), 1);
+
+        let tags: Vec<String> = sqlx::query_scalar("SELECT name FROM tag")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(tags, vec!["work"]);
+
+        let links: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM qu_log_tag")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(links, 1);
+    }
+
+    #[tokio::test]
+    async fn test_qulog_prune() {
+        let pool = in_memory_pool().await.unwrap();
+        let clock = FixedClock(Local.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap());
+        let now = clock.now();
+
+        insert(&pool, "ancient", &(now - Months::new(4)), Vec::new())
+            .await
+            .unwrap();
+        insert(&pool, "recent", &(now - Days::new(30)), Vec::new())
+            .await
+            .unwrap();
+
+        let filter = QuLogFilter {
+            end_date: Some(QuLogRetention::Days(90).cutoff(&clock).unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(delete_logs(&pool, &filter).await.unwrap(), 1);
+
+        let logs = fetch_logs(&pool, &QuLogFilter::default()).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].text, "recent");
+    }
+
+    #[test]
+    fn test_qulog_retention_parsing() {
+        assert_eq!("90d".parse(), Ok(QuLogRetention::Days(90)));
+        assert_eq!("2w".parse(), Ok(QuLogRetention::Weeks(2)));
+        assert_eq!("6m".parse(), Ok(QuLogRetention::Months(6)));
+        assert_eq!("1y".parse(), Ok(QuLogRetention::Years(1)));
+        assert!("90".parse::<QuLogRetention>().is_err());
+        assert!("d".parse::<QuLogRetention>().is_err());
+        assert!("-3d".parse::<QuLogRetention>().is_err());
+        assert!("".parse::<QuLogRetention>().is_err());
+
+        let clock = FixedClock(Local.with_ymd_and_hms(2025, 3, 31, 12, 0, 0).unwrap());
+        assert_eq!(
+            QuLogRetention::Weeks(1).cutoff(&clock).unwrap(),
+            Local.with_ymd_and_hms(2025, 3, 24, 12, 0, 0).unwrap()
+        );
+        assert_eq!(
+            QuLogRetention::Years(1).cutoff(&clock).unwrap(),
+            Local.with_ymd_and_hms(2024, 3, 31, 12, 0, 0).unwrap()
+        );
+        assert!(matches!(
+            QuLogRetention::Weeks(u64::MAX).cutoff(&clock),
+            Err(QuLogError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_qulog_filter_args_parse() {
+        let clock = FixedClock(Local.with_ymd_and_hms(2025, 4, 2, 15, 30, 0).unwrap());
+        let args = |start: Option<&str>, end: Option<&str>| QuLogFilterArgs {
+            tags: None,
+            tag_match: QuLogTagMatch::All,
+            level: None,
+            source: None,
+            fields: Vec::new(),
+            start_date: start.map(String::from),
+            end_date: end.map(String::from),
+            date_range: None,
+            days: None,
+        };
+
+        let filter = args(Some("yesterday"), Some("yesterday"))
+            .parse(&clock, Weekday::Mon)
+            .unwrap();
+        assert_eq!(
+            filter.start_date,
+            Some(Local.with_ymd_and_hms(2025, 4, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            filter.end_date,
+            Some(Local.with_ymd_and_hms(2025, 4, 2, 0, 0, 0).unwrap())
+        );
+
+        let filter = args(None, Some("2025-03-01 10:00:00"))
+            .parse(&clock, Weekday::Mon)
+            .unwrap();
+        assert_eq!(filter.start_date, None);
+        assert_eq!(
+            filter.end_date,
+            Some(Local.with_ymd_and_hms(2025, 3, 1, 10, 0, 0).unwrap() + Duration::nanoseconds(1))
+        );
+
+        assert!(matches!(
+            args(Some("2025-04-31"), None).parse(&clock, Weekday::Mon),
+            Err(QuLogError::Parse(_))
+        ));
+        assert!(matches!(
+            args(None, Some("yesturday")).parse(&clock, Weekday::Mon),
+            Err(QuLogError::Parse(_))
+        ));
+
+        let mut conflicting = args(Some("today"), None);
+        conflicting.date_range = Some(ranges::QuLogDateRange::Today);
+        assert!(matches!(
+            conflicting.parse(&clock, Weekday::Mon),
+            Err(QuLogError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_qulog_update() {
+        let pool = in_memory_pool().await.unwrap();
+        let create_date = Local::now();
+        let tags = vec!["work".to_string(), "typo".to_string()];
+        let id = insert(&pool, "helo world", &create_date, tags)
+            .await
+            .unwrap();
+        insert(&pool, "other", &create_date, vec!["work".to_string()])
+            .await
+            .unwrap();
+
+        let update = QuLogUpdate {
+            text: Some("hello world".to_string()),
+            add_tags: QuLogTags::from("rust,work".to_string()),
+            remove_tags: QuLogTags::from("typo".to_string()),
+        };
+        let edit_date = (create_date + Days::new(1)).to_utc();
+        let log = update_log(&pool, id, &update, edit_date)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(log.id, Some(id));
+        assert_eq!(log.text, "hello world");
+        assert_eq!(log.tags.0, vec!["work", "rust"]);
+        assert_eq!(log.updated_date, Some(edit_date));
+
+        let tags: Vec<String> = sqlx::query_scalar("SELECT name FROM tag ORDER BY name")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(tags, vec!["rust", "work"]);
+
+        let edits = fetch_log_edits(&pool, id).await.unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].old_text, "helo world");
+        assert_eq!(edits[0].new_text, "hello world");
+        assert_eq!(edits[0].old_tags, "work,typo");
+        assert_eq!(edits[0].new_tags, "work,rust");
+
+        let unchanged = QuLogUpdate {
+            add_tags: QuLogTags::from("rust".to_string()),
+            ..Default::default()
+        };
+        update_log(&pool, id, &unchanged, edit_date)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(fetch_log_edits(&pool, id).await.unwrap().len(), 1);
+
+        assert!(update_log(&pool, id + 100, &update, edit_date)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_qulog_read_has_ids() {
+        let pool = in_memory_pool().await.unwrap();
+        let first = insert(&pool, "first", &Local::now(), Vec::new())
+            .await
+            .unwrap();
+        let second = insert(&pool, "second", &Local::now(), Vec::new())
+            .await
+            .unwrap();
+
+        let ids: Vec<Option<i64>> = fetch_logs(&pool, &QuLogFilter::default())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|log| log.id)
+            .collect();
+        assert_eq!(ids, vec![Some(first), Some(second)]);
+
+        let only_second = QuLogFilter::builder().ids([second, second + 10]).build();
+        let logs = fetch_logs(&pool, &only_second).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].text, "second");
+        assert!(only_second.matches(&logs[0]));
+    }
+
+    #[test]
+    fn test_qulog_format_entry() {
+        let tags = QuLogTags::from("work,ops".to_string());
+        assert_eq!(
+            format_entry("-> [1] 04-02 : ", "one line", &tags, " (edited)"),
+            "-> [1] 04-02 : one line [work-ops] (edited)"
+        );
+        assert_eq!(
+            format_entry("-> [2] 04-02 : ", "first\r\n  second\n\nlast", &tags, ""),
+            "-> [2] 04-02 : first\n                 second\n\n               last [work-ops]"
+        );
+
+        let display = dates::QuLogDateDisplay {
+            format: "%m-%d".to_string(),
+            tz: dates::QuLogTimeZone::Original,
+        };
+        let mut log = QuLog::new("disk full", ["ops"]);
+        log.id = Some(3);
+        log.create_date = Utc
+            .with_ymd_and_hms(2025, 4, 2, 0, 0, 0)
+            .unwrap()
+            .fixed_offset();
+        assert_eq!(entry_prefix(&log, &display), "-> [3] 04-02 : ");
+        log.level = QuLogLevel::Error;
+        log.source = Some("web-1".to_string());
+        log.fields.insert("env".to_string(), "prod".to_string());
+        assert_eq!(
+            entry_prefix(&log, &display),
+            "-> [3] 04-02 ERROR (web-1) : "
+        );
+        assert_eq!(format_fields(&log.fields), " env=prod");
+    }
+
+    #[tokio::test]
+    async fn test_qulog_read_sort_and_page() {
+        let pool = in_memory_pool().await.unwrap();
+        let day = |d| Utc.with_ymd_and_hms(2025, 6, d, 12, 0, 0).unwrap();
+        // Saved out of order, "3b" is written at the same time as "3a"
+        for (text, d) in [("3a", 3), ("1", 1), ("5", 5), ("3b", 3), ("2", 2), ("4", 4)] {
+            insert(&pool, text, &day(d), Vec::new()).await.unwrap();
+        }
+
+        let texts = |logs: Vec<QuLog>| logs.into_iter().map(|log| log.text).collect::<Vec<_>>();
+        let all = fetch_logs(&pool, &QuLogFilter::default()).await.unwrap();
+        assert_eq!(texts(all.clone()), vec!["1", "2", "3a", "3b", "4", "5"]);
+
+        let newest = QuLogFilter::builder()
+            .sort(QuLogSort::Desc)
+            .limit(3)
+            .build();
+        assert_eq!(
+            texts(fetch_logs(&pool, &newest).await.unwrap()),
+            vec!["5", "4", "3b"]
+        );
+        let second_page = QuLogFilter::builder().limit(2).offset(2).build();
+        assert_eq!(
+            texts(fetch_logs(&pool, &second_page).await.unwrap()),
+            vec!["3a", "3b"]
+        );
+        let skipped = QuLogFilter::builder()
+            .sort(QuLogSort::Desc)
+            .offset(4)
+            .build();
+        assert_eq!(
+            texts(fetch_logs(&pool, &skipped).await.unwrap()),
+            vec!["2", "1"]
+        );
+
+        // Stores paging in memory pick the same logs
+        for filter in [&newest, &second_page, &skipped] {
+            assert_eq!(
+                filter.select(all.clone()),
+                fetch_logs(&pool, filter).await.unwrap()
+            );
+        }
+
+        let streamed: Vec<QuLog> = stream_logs(&pool, &newest).try_collect().await.unwrap();
+        assert_eq!(streamed, fetch_logs(&pool, &newest).await.unwrap());
+
+        assert_eq!(count_logs(&pool, &newest).await.unwrap(), 3);
+        assert_eq!(delete_logs(&pool, &newest).await.unwrap(), 3);
+        assert_eq!(
+            texts(fetch_logs(&pool, &QuLogFilter::default()).await.unwrap()),
+            vec!["1", "2", "3a"]
+        );
     }
 
     async fn in_memory_pool() -> Result<SqlitePool, sqlx::Error> {
-        let cnfg = DBConfig { in_memory: true };
-        connect_to_db(&cnfg).await
+        connect_to_db(&DBConfig::in_memory()).await
     }
 
-    async fn insert(
+    async fn insert<Tz: TimeZone>(
         pool: &SqlitePool,
         text: &str,
-        create_date: &DateTime<Local>,
+        create_date: &DateTime<Tz>,
         tags: Vec<String>,
-    ) -> Result<(), sqlx::Error> {
-        create_log_table_if_not_exists(&pool)
+    ) -> Result<i64, sqlx::Error> {
+        migrations::migrate(pool)
             .await
-            .expect("Unable to create log data base");
+            .expect("Unable to migrate log data base");
 
         let model = QuLog {
+            id: None,
             text: text.to_string(),
             tags: QuLogTags(tags),
-            create_date: create_date.clone(),
+            level: QuLogLevel::default(),
+            source: None,
+            fields: BTreeMap::new(),
+            attachments: Vec::new(),
+            create_date: create_date.fixed_offset(),
+            updated_date: None,
         };
 
-        create_log(&model, &pool).await
+        create_log(&model, pool).await
     }
 }
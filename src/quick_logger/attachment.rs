@@ -0,0 +1,215 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{sqlite::SqlitePool, FromRow, SqliteConnection};
+
+use super::{error::QuLogError, insert_log, QuLog};
+
+/// Folder next to the html export attachments are written to, shared by every export
+/// of the folder since an attachment never changes once saved.
+pub(super) const EXPORT_DIR: &str = "attachments";
+
+/// A file attached to a log, its content is read with [`QuLogStore::attachment`].
+///
+/// [`QuLogStore::attachment`]: super::QuLogStore::attachment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct QuLogAttachment {
+    pub id: i64,
+    /// Name of the attached file, without its directory
+    pub name: String,
+    /// Size in bytes
+    pub size: i64,
+}
+
+impl QuLogAttachment {
+    /// Name the attachment is written with when exported, prefixed by its id so
+    /// attachments sharing a name don't overwrite each other.
+    pub(super) fn file_name(&self) -> String {
+        let name: String = self
+            .name
+            .chars()
+            .map(|c| match c {
+                c if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') => c,
+                _ => '_',
+            })
+            .collect();
+        format!("{}-{}", self.id, name)
+    }
+
+    /// Whether browsers can show it in an `<img>`, going by its extension.
+    pub(super) fn is_image(&self) -> bool {
+        let extension = Path::new(&self.name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase);
+        matches!(
+            extension.as_deref(),
+            Some("png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" | "bmp")
+        )
+    }
+}
+
+/// A file read from disk to be attached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct QuLogAttachmentFile {
+    pub(super) name: String,
+    pub(super) data: Vec<u8>,
+}
+
+impl QuLogAttachmentFile {
+    pub(super) fn read(path: &Path) -> Result<Self, QuLogError> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or(QuLogError::Validation(format!(
+                "{} is not a file that can be attached",
+                path.display()
+            )))?;
+        let data = std::fs::read(path)
+            .map_err(|err| QuLogError::io(format!("Unable to read {}", path.display()), err))?;
+        Ok(QuLogAttachmentFile { name, data })
+    }
+}
+
+/// `1.5 KiB`, `12 B`...
+pub(super) fn format_size(size: i64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+
+    let mut size = size as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Saves `log` and its `files` in one transaction, nothing is saved when one of them fails.
+pub(super) async fn create_log_with_attachments(
+    pool: &SqlitePool,
+    log: &QuLog,
+    files: &[QuLogAttachmentFile],
+) -> Result<i64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let log_id = insert_log(&mut tx, log).await?;
+    for file in files {
+        insert_attachment(&mut tx, log_id, file).await?;
+    }
+    tx.commit().await?;
+    Ok(log_id)
+}
+
+pub(super) async fn insert_attachment(
+    conn: &mut SqliteConnection,
+    log_id: i64,
+    file: &QuLogAttachmentFile,
+) -> Result<i64, sqlx::Error> {
+    let id = sqlx::query(
+        "INSERT INTO qu_log_attachment (log_id, name, size, data) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(log_id)
+    .bind(&file.name)
+    .bind(file.data.len() as i64)
+    .bind(&file.data)
+    .execute(conn)
+    .await?
+    .last_insert_rowid();
+    Ok(id)
+}
+
+#[derive(FromRow)]
+struct QuLogAttachmentDBO {
+    #[sqlx(flatten)]
+    attachment: QuLogAttachment,
+    data: Vec<u8>,
+}
+
+/// The attachment with `id` and its content.
+pub(super) async fn fetch_attachment(
+    pool: &SqlitePool,
+    id: i64,
+) -> Result<Option<(QuLogAttachment, Vec<u8>)>, sqlx::Error> {
+    let attachment = sqlx::query_as::<_, QuLogAttachmentDBO>(
+        "SELECT id, name, size, data FROM qu_log_attachment WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(attachment.map(|dbo| (dbo.attachment, dbo.data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{connect_to_db, fetch_logs, migrations, DBConfig, QuLogFilter};
+    use super::*;
+
+    #[tokio::test]
+    async fn test_attachments_round_trip() {
+        let pool = connect_to_db(&DBConfig::in_memory()).await.unwrap();
+        migrations::migrate(&pool).await.unwrap();
+
+        let files = [
+            QuLogAttachmentFile {
+                name: "screen shot.png".to_string(),
+                data: vec![0x89, b'P', b'N', b'G'],
+            },
+            QuLogAttachmentFile {
+                name: "notes.txt".to_string(),
+                data: b"hello".to_vec(),
+            },
+        ];
+        let log = QuLog::new("with files", ["bug"]);
+        let log_id = create_log_with_attachments(&pool, &log, &files)
+            .await
+            .unwrap();
+        create_log_with_attachments(&pool, &QuLog::new("without", ["bug"]), &[])
+            .await
+            .unwrap();
+
+        let logs = fetch_logs(&pool, &QuLogFilter::default()).await.unwrap();
+        assert_eq!(logs[0].id, Some(log_id));
+        let attachments = &logs[0].attachments;
+        assert_eq!(attachments.len(), 2);
+        assert_eq!(attachments[0].name, "screen shot.png");
+        assert_eq!(attachments[0].size, 4);
+        assert!(attachments[0].is_image());
+        assert!(!attachments[1].is_image());
+        assert_eq!(
+            attachments[0].file_name(),
+            format!("{}-screen_shot.png", attachments[0].id)
+        );
+        assert!(logs[1].attachments.is_empty());
+
+        let (attachment, data) = fetch_attachment(&pool, attachments[1].id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(attachment, attachments[1]);
+        assert_eq!(data, b"hello");
+        assert!(fetch_attachment(&pool, attachments[1].id + 10)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Deleting the log deletes its attachments
+        let filter = QuLogFilter::builder().ids([log_id]).build();
+        super::super::delete_logs(&pool, &filter).await.unwrap();
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM qu_log_attachment")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(left, 0);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+}
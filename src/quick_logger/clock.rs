@@ -0,0 +1,27 @@
+use chrono::{DateTime, Local, TimeZone};
+
+/// Source of the current time, so date parsing, ranges and new logs can be tested
+/// against a known instant and time zone.
+pub(super) trait Clock<Tz: TimeZone = Local> {
+    fn now(&self) -> DateTime<Tz>;
+}
+
+/// The clock of the machine, in its local time zone.
+pub(super) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// Always tells the same instant, in the time zone it was created with.
+#[cfg(test)]
+pub(super) struct FixedClock<Tz: TimeZone>(pub(super) DateTime<Tz>);
+
+#[cfg(test)]
+impl<Tz: TimeZone> Clock<Tz> for FixedClock<Tz> {
+    fn now(&self) -> DateTime<Tz> {
+        self.0.clone()
+    }
+}
@@ -0,0 +1,519 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    Weekday,
+};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::{dates::QuLogTimeZone, error::QuLogError};
+
+/// Environment variable pointing qulog to a database file.
+pub(super) const DB_ENV_VAR: &str = "QULOG_DB";
+
+pub(super) const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+pub(super) const DEFAULT_CSS_PATH: &str = "resources/table-css.css";
+pub(super) const DEFAULT_EXPORT_DIR: &str = "./exports";
+
+/// Settings read from `config.toml` in the qulog config directory.
+/// Every key can be overridden by its command line flag.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct QuLogConfig {
+    /// Database file, overridden by `--db`
+    pub(super) db_path: Option<PathBuf>,
+    /// Directory exports are written to, overridden by `export --to`
+    pub(super) export_dir: Option<PathBuf>,
+    /// Tags added to logs created without `--tags`
+    pub(super) default_tags: Option<Vec<String>>,
+    /// chrono strftime format logs are printed with, overridden by `--date-format`
+    pub(super) date_format: Option<String>,
+    /// Stylesheet of the html export, overridden by `export --css`
+    pub(super) css_path: Option<PathBuf>,
+    /// tinytemplate file of the html export, overridden by `export --template`
+    pub(super) template_path: Option<PathBuf>,
+    /// First day of this-week and last-week, overridden by `--week-start`
+    pub(super) week_start: Option<Weekday>,
+    /// Time zone dates are printed in, overridden by `--tz`
+    pub(super) time_zone: Option<QuLogTimeZone>,
+}
+
+/// Keys of `QuLogConfig` as written in `config.toml`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub(super) enum QuLogConfigKey {
+    #[value(name = "db_path")]
+    DbPath,
+    #[value(name = "export_dir")]
+    ExportDir,
+    #[value(name = "default_tags")]
+    DefaultTags,
+    #[value(name = "date_format")]
+    DateFormat,
+    #[value(name = "css_path")]
+    CssPath,
+    #[value(name = "template_path")]
+    TemplatePath,
+    #[value(name = "week_start")]
+    WeekStart,
+    #[value(name = "time_zone")]
+    TimeZone,
+}
+
+impl QuLogConfig {
+    /// Reads the config at `path`, a missing file is an empty config.
+    pub(super) fn load(path: &Path) -> Result<Self, QuLogError> {
+        let invalid = |err: &dyn Display| {
+            QuLogError::Parse(format!("Invalid config file {}: {}", path.display(), err))
+        };
+
+        let config: QuLogConfig = match std::fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content).map_err(|err| invalid(&err))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => QuLogConfig::default(),
+            Err(err) => {
+                return Err(QuLogError::io(
+                    format!("Unable to read {}", path.display()),
+                    err,
+                ))
+            }
+        };
+
+        if let Some(format) = &config.date_format {
+            validate_date_format(format).map_err(|err| invalid(&err))?;
+        }
+        Ok(config)
+    }
+
+    /// Sets `key` from its command line form, an empty value removes the key.
+    pub(super) fn set(&mut self, key: QuLogConfigKey, value: &str) -> Result<(), String> {
+        let value = Some(value.trim()).filter(|value| !value.is_empty());
+
+        match key {
+            QuLogConfigKey::DbPath => self.db_path = value.map(PathBuf::from),
+            QuLogConfigKey::ExportDir => self.export_dir = value.map(PathBuf::from),
+            QuLogConfigKey::DefaultTags => {
+                self.default_tags = value.map(|tags| {
+                    tags.split(",")
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+            }
+            QuLogConfigKey::DateFormat => {
+                if let Some(format) = value {
+                    validate_date_format(format)?;
+                }
+                self.date_format = value.map(String::from)
+            }
+            QuLogConfigKey::CssPath => self.css_path = value.map(PathBuf::from),
+            QuLogConfigKey::TemplatePath => self.template_path = value.map(PathBuf::from),
+            QuLogConfigKey::WeekStart => {
+                self.week_start = value.map(super::ranges::parse_week_start).transpose()?
+            }
+            QuLogConfigKey::TimeZone => {
+                self.time_zone = value.map(super::dates::parse_time_zone).transpose()?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `config.toml` as written, for `qulog config` to read and repair a file `QuLogConfig::load`
+/// rejects: unknown keys are kept and values are only checked when they are set or listed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(super) struct QuLogRawConfig(toml::Table);
+
+impl QuLogRawConfig {
+    /// Reads the config at `path`, a missing file is an empty config. Only a file
+    /// that isn't TOML at all fails, since there is no key to read or set in it.
+    pub(super) fn load(path: &Path) -> Result<Self, QuLogError> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => content.parse().map(QuLogRawConfig).map_err(|err| {
+                QuLogError::Parse(format!("Invalid config file {}: {}", path.display(), err))
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(QuLogError::io(
+                format!("Unable to read {}", path.display()),
+                err,
+            )),
+        }
+    }
+
+    pub(super) fn save(&self, path: &Path) -> Result<(), QuLogError> {
+        let content =
+            toml::to_string_pretty(&self.0).map_err(|err| QuLogError::Parse(err.to_string()))?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| {
+                QuLogError::io(format!("Unable to create {}", parent.display()), err)
+            })?;
+        }
+        std::fs::write(path, content)
+            .map_err(|err| QuLogError::io(format!("Unable to write {}", path.display()), err))
+    }
+
+    /// The value of `key` as written, lists joined by commas like `set` takes them.
+    pub(super) fn get(&self, key: QuLogConfigKey) -> Option<String> {
+        let value = self.0.get(&key.name())?;
+        Some(match value {
+            toml::Value::String(value) => value.clone(),
+            toml::Value::Array(values) => values
+                .iter()
+                .map(|value| match value {
+                    toml::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            value => value.to_string(),
+        })
+    }
+
+    /// Sets `key` from its command line form, validated like `QuLogConfig::set`,
+    /// an empty value removes the key.
+    pub(super) fn set(&mut self, key: QuLogConfigKey, value: &str) -> Result<(), String> {
+        let mut config = QuLogConfig::default();
+        config.set(key, value)?;
+
+        let name = key.name();
+        let written = toml::Table::try_from(&config).map_err(|err| err.to_string())?;
+        match written.get(&name) {
+            Some(value) => self.0.insert(name, value.clone()),
+            None => self.0.remove(&name),
+        };
+        Ok(())
+    }
+
+    /// Why the value of `key` is rejected when qulog reads the config, if it is.
+    pub(super) fn check(&self, key: QuLogConfigKey) -> Result<(), String> {
+        let Some(value) = self.0.get(&key.name()) else {
+            return Ok(());
+        };
+        let mut single = toml::Table::new();
+        single.insert(key.name(), value.clone());
+        let config: QuLogConfig = single
+            .try_into()
+            .map_err(|err: toml::de::Error| err.message().to_string())?;
+        match &config.date_format {
+            Some(format) => validate_date_format(format),
+            None => Ok(()),
+        }
+    }
+
+    /// Keys qulog doesn't know, which have to be removed from the file by hand.
+    pub(super) fn unknown_keys(&self) -> impl Iterator<Item = (&String, &toml::Value)> {
+        self.0.iter().filter(|(name, _)| {
+            !QuLogConfigKey::value_variants()
+                .iter()
+                .any(|key| key.name() == **name)
+        })
+    }
+}
+
+impl QuLogConfigKey {
+    /// Name of the key in `config.toml`
+    pub(super) fn name(self) -> String {
+        self.to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// chrono panics while printing a date with an invalid format, so formats are checked up front.
+pub(super) fn validate_date_format(format: &str) -> Result<(), String> {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("invalid date format {:?}", format));
+    }
+    Ok(())
+}
+
+/// Where the database path in use came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum DBPathSource {
+    CliFlag,
+    EnvVar,
+    ConfigFile(PathBuf),
+    Default,
+    /// Opened with `QuLogStore::open`
+    Library,
+}
+
+impl Display for DBPathSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DBPathSource::CliFlag => write!(f, "--db"),
+            DBPathSource::EnvVar => write!(f, "{}", DB_ENV_VAR),
+            DBPathSource::ConfigFile(path) => write!(f, "db_path in {}", path.display()),
+            DBPathSource::Default => write!(f, "default"),
+            DBPathSource::Library => write!(f, "QuLogStore::open"),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/qulog/config.toml`, falling back to `~/.config/qulog/config.toml`.
+pub(super) fn config_file_path(env: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    xdg_dir(env, "XDG_CONFIG_HOME", ".config").map(|dir| dir.join("qulog").join("config.toml"))
+}
+
+/// `$XDG_DATA_HOME/qulog/logs.db`, falling back to `~/.local/share/qulog/logs.db`.
+pub(super) fn default_db_path(env: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    xdg_dir(env, "XDG_DATA_HOME", ".local/share").map(|dir| dir.join("qulog").join("logs.db"))
+}
+
+/// Picks the database path from, in order: `--db`, `$QULOG_DB`, `db_path` of the config file
+/// and the XDG data directory. `./logs.db` is only used when no home directory is known.
+pub(super) fn resolve_db_path(
+    cli_db: Option<PathBuf>,
+    env: &dyn Fn(&str) -> Option<String>,
+    config: &QuLogConfig,
+    config_path: Option<&Path>,
+) -> (PathBuf, DBPathSource) {
+    if let Some(path) = cli_db {
+        return (path, DBPathSource::CliFlag);
+    }
+
+    if let Some(path) = env(DB_ENV_VAR).filter(|path| !path.is_empty()) {
+        return (expand_home(Path::new(&path), env), DBPathSource::EnvVar);
+    }
+
+    if let Some(path) = &config.db_path {
+        let source =
+            DBPathSource::ConfigFile(config_path.map(Path::to_path_buf).unwrap_or_default());
+        return (expand_home(path, env), source);
+    }
+
+    match default_db_path(env) {
+        Some(path) => (path, DBPathSource::Default),
+        None => (PathBuf::from("logs.db"), DBPathSource::Default),
+    }
+}
+
+/// XDG base directory from `var` when it's an absolute path, otherwise `$HOME/fallback`.
+fn xdg_dir(env: &dyn Fn(&str) -> Option<String>, var: &str, fallback: &str) -> Option<PathBuf> {
+    env(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home_dir(env).map(|home| home.join(fallback)))
+}
+
+fn home_dir(env: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    env("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+pub(super) fn expand_home(path: &Path, env: &dyn Fn(&str) -> Option<String>) -> PathBuf {
+    match (path.strip_prefix("~"), home_dir(env)) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn env_of(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn test_resolve_db_path_precedence() {
+        let env = env_of(&[("HOME", "/home/me"), (DB_ENV_VAR, "/env/logs.db")]);
+        let config = QuLogConfig {
+            db_path: Some(PathBuf::from("/config/logs.db")),
+            ..Default::default()
+        };
+        let config_path = Path::new("/home/me/.config/qulog/config.toml");
+
+        assert_eq!(
+            resolve_db_path(Some("cli.db".into()), &env, &config, Some(config_path)),
+            (PathBuf::from("cli.db"), DBPathSource::CliFlag)
+        );
+        assert_eq!(
+            resolve_db_path(None, &env, &config, Some(config_path)),
+            (PathBuf::from("/env/logs.db"), DBPathSource::EnvVar)
+        );
+
+        let env = env_of(&[("HOME", "/home/me")]);
+        assert_eq!(
+            resolve_db_path(None, &env, &config, Some(config_path)),
+            (
+                PathBuf::from("/config/logs.db"),
+                DBPathSource::ConfigFile(config_path.to_path_buf())
+            )
+        );
+        assert_eq!(
+            resolve_db_path(None, &env, &QuLogConfig::default(), None),
+            (
+                PathBuf::from("/home/me/.local/share/qulog/logs.db"),
+                DBPathSource::Default
+            )
+        );
+    }
+
+    #[test]
+    fn test_resolve_db_path_xdg() {
+        let env = env_of(&[("HOME", "/home/me"), ("XDG_DATA_HOME", "/data")]);
+        assert_eq!(
+            resolve_db_path(None, &env, &QuLogConfig::default(), None).0,
+            PathBuf::from("/data/qulog/logs.db")
+        );
+
+        // Relative XDG directories are invalid and ignored.
+        let env = env_of(&[("HOME", "/home/me"), ("XDG_DATA_HOME", "data")]);
+        assert_eq!(
+            resolve_db_path(None, &env, &QuLogConfig::default(), None).0,
+            PathBuf::from("/home/me/.local/share/qulog/logs.db")
+        );
+
+        let env = env_of(&[]);
+        assert_eq!(
+            resolve_db_path(None, &env, &QuLogConfig::default(), None).0,
+            PathBuf::from("logs.db")
+        );
+
+        let env = env_of(&[("HOME", "/home/me"), ("XDG_CONFIG_HOME", "/conf")]);
+        assert_eq!(
+            config_file_path(&env),
+            Some(PathBuf::from("/conf/qulog/config.toml"))
+        );
+    }
+
+    #[test]
+    fn test_resolve_db_path_expands_home() {
+        let env = env_of(&[("HOME", "/home/me"), (DB_ENV_VAR, "~/notes/logs.db")]);
+        assert_eq!(
+            resolve_db_path(None, &env, &QuLogConfig::default(), None).0,
+            PathBuf::from("/home/me/notes/logs.db")
+        );
+    }
+
+    #[test]
+    fn test_config_load() {
+        let dir = std::env::temp_dir().join(format!("qulog-config-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let missing = QuLogConfig::load(&dir.join("missing.toml")).unwrap();
+        assert!(missing.db_path.is_none());
+
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "db_path = \"/tmp/logs.db\"\n").unwrap();
+        let config = QuLogConfig::load(&path).unwrap();
+        assert_eq!(config.db_path, Some(PathBuf::from("/tmp/logs.db")));
+
+        std::fs::write(&path, "db_path = ").unwrap();
+        assert!(QuLogConfig::load(&path).is_err());
+
+        std::fs::write(&path, "unknown_key = 1").unwrap();
+        assert!(QuLogConfig::load(&path).is_err());
+
+        std::fs::write(&path, "date_format = \"%Y %Q\"").unwrap();
+        assert!(QuLogConfig::load(&path).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_set_get_and_save() {
+        // Keys are set through the raw config like `qulog config set` does,
+        // and the typed one tells what qulog reads back
+        let mut config = QuLogConfig::default();
+        let mut raw = QuLogRawConfig::default();
+        let mut set = |key, value: &str| {
+            let typed = config.set(key, value);
+            assert_eq!(raw.set(key, value), typed);
+            typed
+        };
+        set(QuLogConfigKey::DefaultTags, "work, daily,").unwrap();
+        set(QuLogConfigKey::DateFormat, "%d/%m %H:%M").unwrap();
+        set(QuLogConfigKey::ExportDir, "~/exports").unwrap();
+        assert!(set(QuLogConfigKey::DateFormat, "%Q").is_err());
+        set(QuLogConfigKey::ExportDir, "").unwrap();
+        set(QuLogConfigKey::TimeZone, "Europe/Berlin").unwrap();
+        assert!(set(QuLogConfigKey::TimeZone, "Mars/Olympus").is_err());
+
+        assert_eq!(
+            config.default_tags,
+            Some(vec!["work".to_string(), "daily".to_string()])
+        );
+        assert_eq!(
+            raw.get(QuLogConfigKey::DefaultTags),
+            Some("work,daily".to_string())
+        );
+        assert_eq!(
+            raw.get(QuLogConfigKey::DateFormat),
+            Some("%d/%m %H:%M".to_string())
+        );
+        assert_eq!(raw.get(QuLogConfigKey::CssPath), None);
+        assert_eq!(config.export_dir, None);
+        assert_eq!(raw.get(QuLogConfigKey::ExportDir), None);
+        assert_eq!(
+            config.time_zone,
+            Some(QuLogTimeZone::Named(chrono_tz::Europe::Berlin))
+        );
+
+        let dir = std::env::temp_dir().join(format!("qulog-config-save-{}", std::process::id()));
+        let path = dir.join("qulog").join("config.toml");
+        raw.save(&path).unwrap();
+        assert_eq!(QuLogConfig::load(&path).unwrap(), config);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_raw_config_repairs_invalid_file() {
+        let dir = std::env::temp_dir().join(format!("qulog-config-raw-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(
+            &path,
+            "bogus_key = 1\ndate_format = \"%Y %Q\"\ndefault_tags = [\"work\", \"daily\"]\n",
+        )
+        .unwrap();
+        assert!(QuLogConfig::load(&path).is_err());
+
+        let mut raw = QuLogRawConfig::load(&path).unwrap();
+        assert_eq!(
+            raw.get(QuLogConfigKey::DefaultTags),
+            Some("work,daily".to_string())
+        );
+        assert_eq!(
+            raw.get(QuLogConfigKey::DateFormat),
+            Some("%Y %Q".to_string())
+        );
+        assert!(raw.check(QuLogConfigKey::DateFormat).is_err());
+        assert!(raw.check(QuLogConfigKey::DefaultTags).is_ok());
+        let unknown: Vec<&String> = raw.unknown_keys().map(|(name, _)| name).collect();
+        assert_eq!(unknown, vec!["bogus_key"]);
+
+        // Set still validates what it writes
+        assert!(raw.set(QuLogConfigKey::DateFormat, "%Q").is_err());
+        raw.set(QuLogConfigKey::DateFormat, "%Y").unwrap();
+        raw.set(QuLogConfigKey::WeekStart, "sunday").unwrap();
+        assert!(raw.check(QuLogConfigKey::WeekStart).is_ok());
+        raw.save(&path).unwrap();
+
+        // Only the unknown key is left to fix, by hand
+        let raw = QuLogRawConfig::load(&path).unwrap();
+        assert_eq!(raw.get(QuLogConfigKey::DateFormat), Some("%Y".to_string()));
+        assert_eq!(raw.unknown_keys().count(), 1);
+        std::fs::write(&path, "date_format = \"%Y\"\n").unwrap();
+        assert!(QuLogConfig::load(&path).is_ok());
+
+        std::fs::write(&path, "date_format = ").unwrap();
+        assert!(QuLogRawConfig::load(&path).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,424 @@
+use std::fmt::Display;
+
+use chrono::{
+    DateTime, Datelike, Days, Duration, FixedOffset, Local, Months, NaiveDate, NaiveDateTime,
+    NaiveTime, TimeZone, Utc, Weekday,
+};
+use serde::{Deserialize, Serialize};
+
+use super::clock::Clock;
+
+/// Time covered by a date written on the command line, `2025-04-02` covers the whole day
+/// while `2025-04-02 10:30:00` or `2 hours ago` is a single instant, ending a nanosecond after it.
+/// `start` is inclusive, `end` is the first instant after the span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct QuLogDateSpan<Tz: TimeZone> {
+    pub(super) start: DateTime<Tz>,
+    pub(super) end: DateTime<Tz>,
+}
+
+const EXPECTED: &str = "expected YYYY-MM-DD, YYYY-MM-DD HH:MM[:SS], an ISO 8601 date with offset, \
+    YYYY-Www, today, yesterday, tomorrow, now, [last] <weekday> or <n> <unit>s ago";
+
+/// Parses `input` relative to the time of `clock`, in its time zone.
+/// Accepts `2025-04-02`, `2025-04-02 10:30[:00]`, ISO 8601 like `2025-04-02T10:30:00+02:00`,
+/// ISO weeks like `2025-W14`, `today`, `yesterday`, `tomorrow`, `now`, `monday`,
+/// `last monday` and `3 days ago`. Anything else is an error, never a missing filter.
+pub(super) fn parse_date<Tz: TimeZone>(
+    input: &str,
+    clock: &dyn Clock<Tz>,
+) -> Result<QuLogDateSpan<Tz>, String> {
+    let now = &clock.now();
+    let input = input.trim();
+    let error = || format!("invalid date {:?}, {}", input, EXPECTED);
+    let tz = now.timezone();
+
+    if let Ok(date) = DateTime::parse_from_rfc3339(input) {
+        return Ok(QuLogDateSpan::instant(date.with_timezone(&tz)));
+    }
+
+    for format in [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(input, format) {
+            return Ok(QuLogDateSpan::instant(local_datetime(&tz, date)));
+        }
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return Ok(QuLogDateSpan::days(&tz, date, 1));
+    }
+
+    if let Some(span) = parse_iso_week(&tz, input) {
+        return span.ok_or_else(error);
+    }
+
+    let words: Vec<String> = input
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect();
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    let today = now.date_naive();
+
+    match words.as_slice() {
+        ["now"] => Ok(QuLogDateSpan::instant(now.clone())),
+        ["today"] => Ok(QuLogDateSpan::days(&tz, today, 1)),
+        ["yesterday"] => Ok(QuLogDateSpan::days(&tz, today - Days::new(1), 1)),
+        ["tomorrow"] => Ok(QuLogDateSpan::days(&tz, today + Days::new(1), 1)),
+        [weekday] => {
+            // The latest one, today included
+            let weekday: Weekday = weekday.parse().map_err(|_| error())?;
+            let days = today.weekday().days_since(weekday);
+            Ok(QuLogDateSpan::days(&tz, today - Days::new(days as u64), 1))
+        }
+        ["last", weekday] => {
+            // The latest one before today
+            let weekday: Weekday = weekday.parse().map_err(|_| error())?;
+            let days = match today.weekday().days_since(weekday) {
+                0 => 7,
+                days => days,
+            };
+            Ok(QuLogDateSpan::days(&tz, today - Days::new(days as u64), 1))
+        }
+        [count, unit, "ago"] => {
+            let count: u32 = match *count {
+                "a" | "an" => 1,
+                count => count.parse().map_err(|_| error())?,
+            };
+            parse_ago(now, count, unit).ok_or_else(error)
+        }
+        _ => Err(error()),
+    }
+}
+
+impl<Tz: TimeZone> QuLogDateSpan<Tz> {
+    fn instant(date: DateTime<Tz>) -> Self {
+        QuLogDateSpan {
+            end: date.clone() + Duration::nanoseconds(1),
+            start: date,
+        }
+    }
+
+    /// `count` whole days starting at `date`, from midnight to midnight.
+    pub(super) fn days(tz: &Tz, date: NaiveDate, count: u64) -> Self {
+        Self::between(tz, date, date + Days::new(count))
+    }
+
+    /// From the midnight starting `start` to the one starting `end`.
+    pub(super) fn between(tz: &Tz, start: NaiveDate, end: NaiveDate) -> Self {
+        QuLogDateSpan {
+            start: local_datetime(tz, start.and_time(NaiveTime::MIN)),
+            end: local_datetime(tz, end.and_time(NaiveTime::MIN)),
+        }
+    }
+}
+
+/// `2025-W14` is the ISO week from its monday to the next one.
+/// `None` when `input` isn't written like a week, `Some(None)` for a week that doesn't exist.
+fn parse_iso_week<Tz: TimeZone>(tz: &Tz, input: &str) -> Option<Option<QuLogDateSpan<Tz>>> {
+    let (year, week) = input.split_once(['W', 'w'])?;
+    let year: i32 = year.strip_suffix('-')?.parse().ok()?;
+    let week: u32 = week.parse().ok()?;
+
+    Some(
+        NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)
+            .map(|monday| QuLogDateSpan::days(tz, monday, 7)),
+    )
+}
+
+/// Minutes and hours ago are instants, bigger units are the whole day they land on.
+fn parse_ago<Tz: TimeZone>(
+    now: &DateTime<Tz>,
+    count: u32,
+    unit: &str,
+) -> Option<QuLogDateSpan<Tz>> {
+    let tz = now.timezone();
+    let today = now.date_naive();
+    let unit = unit.strip_suffix('s').unwrap_or(unit);
+
+    let day = match unit {
+        "minute" | "min" => {
+            let ago = Duration::try_minutes(count.into())?;
+            return Some(QuLogDateSpan::instant(now.clone().checked_sub_signed(ago)?));
+        }
+        "hour" => {
+            let ago = Duration::try_hours(count.into())?;
+            return Some(QuLogDateSpan::instant(now.clone().checked_sub_signed(ago)?));
+        }
+        "day" => today.checked_sub_days(Days::new(count.into()))?,
+        "week" => today.checked_sub_days(Days::new(7 * count as u64))?,
+        "month" => today.checked_sub_months(Months::new(count))?,
+        "year" => today.checked_sub_months(Months::new(count.checked_mul(12)?))?,
+        _ => return None,
+    };
+
+    Some(QuLogDateSpan::days(&tz, day, 1))
+}
+
+/// Local time `date` in `tz`. Ambiguous times take the earliest instant and times skipped
+/// by a DST change move forward by the size of the gap, like a wall clock does.
+pub(super) fn local_datetime<Tz: TimeZone>(tz: &Tz, date: NaiveDateTime) -> DateTime<Tz> {
+    let mut shifted = date;
+    loop {
+        if let Some(local) = tz.from_local_datetime(&shifted).earliest() {
+            return local;
+        }
+        shifted += Duration::minutes(15);
+    }
+}
+
+/// Time zone dates are printed in. Logs are stored in UTC along with the offset
+/// they were written with, `Original` prints them with that offset.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub(super) enum QuLogTimeZone {
+    #[default]
+    Local,
+    Utc,
+    Original,
+    /// An IANA time zone like Europe/Berlin
+    Named(chrono_tz::Tz),
+}
+
+/// Parses `local`, `utc`, `original` or an IANA name for `--tz`.
+pub(super) fn parse_time_zone(value: &str) -> Result<QuLogTimeZone, String> {
+    match value.trim().to_lowercase().as_str() {
+        "local" => Ok(QuLogTimeZone::Local),
+        "utc" => Ok(QuLogTimeZone::Utc),
+        "original" => Ok(QuLogTimeZone::Original),
+        _ => value.trim().parse().map(QuLogTimeZone::Named).map_err(|_| {
+            format!(
+                "invalid time zone {:?}, expected local, utc, original or a name like Europe/Berlin",
+                value
+            )
+        }),
+    }
+}
+
+impl TryFrom<String> for QuLogTimeZone {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        parse_time_zone(&value)
+    }
+}
+
+impl From<QuLogTimeZone> for String {
+    fn from(tz: QuLogTimeZone) -> Self {
+        tz.to_string()
+    }
+}
+
+impl Display for QuLogTimeZone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuLogTimeZone::Local => write!(f, "local"),
+            QuLogTimeZone::Utc => write!(f, "utc"),
+            QuLogTimeZone::Original => write!(f, "original"),
+            QuLogTimeZone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+/// How dates are printed: a chrono strftime `format` in the time zone `tz`.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct QuLogDateDisplay {
+    pub(super) format: String,
+    pub(super) tz: QuLogTimeZone,
+}
+
+impl QuLogDateDisplay {
+    /// `date` moved to the display time zone, dates without an original offset
+    /// like edit dates are UTC when the time zone is `Original`.
+    pub(super) fn convert(&self, date: impl Into<DateTime<FixedOffset>>) -> DateTime<FixedOffset> {
+        let date = date.into();
+        match self.tz {
+            QuLogTimeZone::Local => date.with_timezone(&Local).fixed_offset(),
+            QuLogTimeZone::Utc => date.with_timezone(&Utc).fixed_offset(),
+            QuLogTimeZone::Original => date,
+            QuLogTimeZone::Named(tz) => date.with_timezone(&tz).fixed_offset(),
+        }
+    }
+
+    pub(super) fn format(&self, date: impl Into<DateTime<FixedOffset>>) -> String {
+        self.convert(date).format(&self.format).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, Utc};
+
+    use super::super::clock::FixedClock;
+    use super::*;
+
+    /// A wednesday afternoon
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 4, 2, 15, 30, 0).unwrap()
+    }
+
+    fn clock() -> FixedClock<Utc> {
+        FixedClock(now())
+    }
+
+    fn at(month: u32, day: u32, hour: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, month, day, hour, min, 0)
+            .unwrap()
+    }
+
+    /// Whole days from `(month, day)` to the midnight starting `end`
+    fn days(start: (u32, u32), end: (u32, u32)) -> Result<QuLogDateSpan<Utc>, String> {
+        Ok(QuLogDateSpan {
+            start: at(start.0, start.1, 0, 0),
+            end: at(end.0, end.1, 0, 0),
+        })
+    }
+
+    fn instant(date: DateTime<Utc>) -> Result<QuLogDateSpan<Utc>, String> {
+        Ok(QuLogDateSpan {
+            start: date,
+            end: date + Duration::nanoseconds(1),
+        })
+    }
+
+    #[test]
+    fn test_parse_absolute_dates() {
+        assert_eq!(parse_date("2025-04-01", &clock()), days((4, 1), (4, 2)));
+        assert_eq!(
+            parse_date("2025-04-01 10:30:00", &clock()),
+            instant(at(4, 1, 10, 30))
+        );
+        assert_eq!(
+            parse_date("2025-04-01T10:30", &clock()),
+            instant(at(4, 1, 10, 30))
+        );
+        assert_eq!(
+            parse_date("2025-04-01T12:30:00+02:00", &clock()),
+            instant(at(4, 1, 10, 30))
+        );
+        assert_eq!(
+            parse_date("2025-04-01T10:30:00Z", &clock()),
+            instant(at(4, 1, 10, 30))
+        );
+    }
+
+    #[test]
+    fn test_parse_iso_week() {
+        assert_eq!(parse_date("2025-W14", &clock()), days((3, 31), (4, 7)));
+        assert_eq!(
+            parse_date("2025-w01", &clock()).unwrap().start,
+            Utc.with_ymd_and_hms(2024, 12, 30, 0, 0, 0).unwrap()
+        );
+        assert!(parse_date("2025-W54", &clock()).is_err());
+    }
+
+    #[test]
+    fn test_parse_relative_dates() {
+        assert_eq!(parse_date("now", &clock()), instant(now()));
+        assert_eq!(parse_date("Today", &clock()), days((4, 2), (4, 3)));
+        assert_eq!(parse_date("yesterday", &clock()), days((4, 1), (4, 2)));
+        assert_eq!(parse_date("tomorrow", &clock()), days((4, 3), (4, 4)));
+
+        assert_eq!(parse_date("monday", &clock()), days((3, 31), (4, 1)));
+        assert_eq!(parse_date("last mon", &clock()), days((3, 31), (4, 1)));
+        assert_eq!(parse_date("wednesday", &clock()), days((4, 2), (4, 3)));
+        assert_eq!(
+            parse_date("last wednesday", &clock()),
+            days((3, 26), (3, 27))
+        );
+
+        assert_eq!(parse_date("3 days ago", &clock()), days((3, 30), (3, 31)));
+        assert_eq!(parse_date("1 week ago", &clock()), days((3, 26), (3, 27)));
+        assert_eq!(parse_date("a month ago", &clock()), days((3, 2), (3, 3)));
+        assert_eq!(
+            parse_date("2 hours ago", &clock()),
+            instant(at(4, 2, 13, 30))
+        );
+        assert_eq!(
+            parse_date("45 mins ago", &clock()),
+            instant(at(4, 2, 14, 45))
+        );
+    }
+
+    #[test]
+    fn test_parse_uses_time_zone_of_now() {
+        let tz = FixedOffset::east_opt(2 * 3600).unwrap();
+        let span = parse_date("today", &FixedClock(now().with_timezone(&tz))).unwrap();
+        assert_eq!(
+            span.start,
+            tz.with_ymd_and_hms(2025, 4, 2, 0, 0, 0).unwrap()
+        );
+        assert_eq!(span.start.with_timezone(&Utc), at(4, 1, 22, 0));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for input in [
+            "",
+            "2025-13-01",
+            "2025-04-02 25:00",
+            "02/04/2025",
+            "someday",
+            "last week",
+            "3 days",
+            "x days ago",
+            "2 fortnights ago",
+            // Out of the range of dates rather than overflowing
+            "4294967295 years ago",
+            "4294967295 hours ago",
+            "4294967295 weeks ago",
+        ] {
+            let err = parse_date(input, &clock()).unwrap_err();
+            assert!(err.starts_with("invalid date"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_parse_time_zone() {
+        assert_eq!(parse_time_zone("UTC"), Ok(QuLogTimeZone::Utc));
+        assert_eq!(parse_time_zone("local"), Ok(QuLogTimeZone::Local));
+        assert_eq!(parse_time_zone("original"), Ok(QuLogTimeZone::Original));
+        assert_eq!(
+            parse_time_zone("America/Sao_Paulo"),
+            Ok(QuLogTimeZone::Named(chrono_tz::America::Sao_Paulo))
+        );
+        assert!(parse_time_zone("Mars/Olympus").is_err());
+        assert!(parse_time_zone("").is_err());
+
+        let tz = QuLogTimeZone::Named(chrono_tz::Europe::Berlin);
+        assert_eq!(parse_time_zone(&tz.to_string()), Ok(tz));
+    }
+
+    #[test]
+    fn test_display_converts_to_time_zone() {
+        let written = FixedOffset::west_opt(3 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 4, 2, 22, 30, 0)
+            .unwrap();
+        let display = |tz| QuLogDateDisplay {
+            format: "%Y-%m-%d %H:%M %:z".to_string(),
+            tz,
+        };
+
+        assert_eq!(
+            display(QuLogTimeZone::Original).format(written),
+            "2025-04-02 22:30 -03:00"
+        );
+        assert_eq!(
+            display(QuLogTimeZone::Utc).format(written),
+            "2025-04-03 01:30 +00:00"
+        );
+        assert_eq!(
+            display(QuLogTimeZone::Named(chrono_tz::Asia::Kolkata)).format(written),
+            "2025-04-03 07:00 +05:30"
+        );
+        // Dates without an original offset stay in UTC
+        assert_eq!(
+            display(QuLogTimeZone::Original).format(written.to_utc()),
+            "2025-04-03 01:30 +00:00"
+        );
+    }
+}
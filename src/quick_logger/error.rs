@@ -0,0 +1,108 @@
+use std::fmt::Display;
+
+/// Why a qulog command failed. Every kind exits with its own code,
+/// so scripts can tell a bad date from a locked database.
+#[derive(Debug)]
+pub enum QuLogError {
+    /// The database couldn't be opened, migrated, read or written
+    Db(sqlx::Error),
+    /// A file or the terminal couldn't be read or written
+    Io {
+        context: String,
+        source: std::io::Error,
+    },
+    /// A date, search query, config file or imported file couldn't be read
+    Parse(String),
+    /// Arguments or settings that can't be used, like a missing export directory
+    Validation(String),
+    /// The export couldn't be rendered
+    Export(String),
+}
+
+impl QuLogError {
+    /// Exit code of the process: 2 for validation errors (like clap's usage errors),
+    /// 3 for parse, 4 for database, 5 for io and 6 for export errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            QuLogError::Validation(_) => 2,
+            QuLogError::Parse(_) => 3,
+            QuLogError::Db(_) => 4,
+            QuLogError::Io { .. } => 5,
+            QuLogError::Export(_) => 6,
+        }
+    }
+
+    /// An io error with what was being done, like `Unable to read logs.csv`.
+    pub(super) fn io(context: impl Into<String>, source: std::io::Error) -> Self {
+        QuLogError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+}
+
+impl Display for QuLogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuLogError::Db(err) => write!(f, "Database error: {}", err),
+            QuLogError::Io { context, source } => write!(f, "{}: {}", context, source),
+            QuLogError::Parse(message)
+            | QuLogError::Validation(message)
+            | QuLogError::Export(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for QuLogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QuLogError::Db(err) => Some(err),
+            QuLogError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for QuLogError {
+    fn from(err: sqlx::Error) -> Self {
+        QuLogError::Db(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            QuLogError::Db(sqlx::Error::PoolClosed),
+            QuLogError::io(
+                "Unable to read logs.csv",
+                std::io::ErrorKind::NotFound.into(),
+            ),
+            QuLogError::Parse("invalid date".to_string()),
+            QuLogError::Validation("invalid export path".to_string()),
+            QuLogError::Export("invalid template".to_string()),
+        ];
+
+        let mut codes: Vec<i32> = errors.iter().map(QuLogError::exit_code).collect();
+        assert!(codes.iter().all(|code| *code > 1));
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+    }
+
+    #[test]
+    fn test_display() {
+        let err = QuLogError::io(
+            "Unable to read logs.csv",
+            std::io::ErrorKind::NotFound.into(),
+        );
+        assert_eq!(err.to_string(), "Unable to read logs.csv: entity not found");
+        assert_eq!(
+            QuLogError::Parse("invalid date \"someday\"".to_string()).to_string(),
+            "invalid date \"someday\""
+        );
+    }
+}
@@ -0,0 +1,716 @@
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tinytemplate::TinyTemplate;
+
+use super::{attachment, dates::QuLogDateDisplay, QuLog, QuLogLevel, QuLogTags};
+
+/// Turns fetched logs into the content of an export file.
+pub(super) trait QuLogExporter {
+    /// Extension of the written file, without the dot
+    fn extension(&self) -> &'static str;
+
+    fn export(&self, logs: &[QuLog]) -> Result<String, String>;
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub(super) enum QuLogExportFormat {
+    #[default]
+    Html,
+    Csv,
+    Json,
+    Ndjson,
+    Markdown,
+}
+
+/// Template shipped with qulog, used when none is configured.
+pub(super) const DEFAULT_HTML_TEMPLATE: &str = include_str!("../../resources/qulog-export.html");
+
+/// Look of the html export: a tinytemplate `template` and the css inlined in it as `{style}`.
+pub(super) struct QuLogHtmlTheme {
+    pub(super) template: String,
+    pub(super) style: String,
+}
+
+impl Default for QuLogHtmlTheme {
+    fn default() -> Self {
+        QuLogHtmlTheme {
+            template: DEFAULT_HTML_TEMPLATE.to_string(),
+            style: String::new(),
+        }
+    }
+}
+
+impl QuLogExportFormat {
+    /// `display` only applies to the formats meant to be read by people,
+    /// the data formats keep RFC 3339 dates with their original offset so they can be parsed back.
+    pub(super) fn exporter(
+        self,
+        display: &QuLogDateDisplay,
+        theme: QuLogHtmlTheme,
+    ) -> Box<dyn QuLogExporter> {
+        match self {
+            QuLogExportFormat::Html => Box::new(HtmlExporter {
+                display: display.clone(),
+                theme,
+            }),
+            QuLogExportFormat::Csv => Box::new(CsvExporter),
+            QuLogExportFormat::Json => Box::new(JsonExporter),
+            QuLogExportFormat::Ndjson => Box::new(NdjsonExporter),
+            QuLogExportFormat::Markdown => Box::new(MarkdownExporter {
+                display: display.clone(),
+            }),
+        }
+    }
+}
+
+/// Flat shape of a log in the data formats, also read back by `import`.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct QuLogRecord {
+    #[serde(default)]
+    pub(super) id: Option<i64>,
+    pub(super) text: String,
+    #[serde(default)]
+    pub(super) tags: Vec<String>,
+    #[serde(default)]
+    pub(super) level: QuLogLevel,
+    #[serde(default)]
+    pub(super) source: Option<String>,
+    #[serde(default)]
+    pub(super) fields: BTreeMap<String, String>,
+    pub(super) create_date: String,
+    #[serde(default)]
+    pub(super) updated_date: Option<String>,
+}
+
+impl From<&QuLog> for QuLogRecord {
+    fn from(log: &QuLog) -> Self {
+        QuLogRecord {
+            id: log.id,
+            text: log.text.clone(),
+            tags: log.tags.0.clone(),
+            level: log.level,
+            source: log.source.clone(),
+            fields: log.fields.clone(),
+            create_date: log.create_date.to_rfc3339(),
+            updated_date: log.updated_date.map(|date| date.to_rfc3339()),
+        }
+    }
+}
+
+/// CSV has no lists, tags are joined like `--tags` takes them and fields are a JSON object.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct QuLogCsvRecord {
+    #[serde(default)]
+    pub(super) id: Option<i64>,
+    pub(super) text: String,
+    #[serde(default)]
+    pub(super) tags: String,
+    #[serde(default)]
+    pub(super) level: QuLogLevel,
+    #[serde(default)]
+    pub(super) source: Option<String>,
+    #[serde(default)]
+    pub(super) fields: String,
+    pub(super) create_date: String,
+    #[serde(default)]
+    pub(super) updated_date: Option<String>,
+}
+
+impl From<QuLogRecord> for QuLogCsvRecord {
+    fn from(record: QuLogRecord) -> Self {
+        QuLogCsvRecord {
+            id: record.id,
+            text: record.text,
+            tags: record.tags.join(","),
+            level: record.level,
+            source: record.source,
+            fields: if record.fields.is_empty() {
+                String::new()
+            } else {
+                serde_json::to_string(&record.fields).unwrap_or_default()
+            },
+            create_date: record.create_date,
+            updated_date: record.updated_date,
+        }
+    }
+}
+
+impl TryFrom<QuLogCsvRecord> for QuLogRecord {
+    type Error = String;
+
+    fn try_from(record: QuLogCsvRecord) -> Result<Self, Self::Error> {
+        let fields = match record.fields.trim() {
+            "" => BTreeMap::new(),
+            fields => serde_json::from_str(fields)
+                .map_err(|err| format!("invalid fields {:?}: {}", fields, err))?,
+        };
+
+        Ok(QuLogRecord {
+            id: record.id,
+            text: record.text,
+            tags: QuLogTags::from(record.tags).0,
+            level: record.level,
+            source: record.source.filter(|source| !source.is_empty()),
+            fields,
+            create_date: record.create_date,
+            updated_date: record.updated_date,
+        })
+    }
+}
+
+struct HtmlExporter {
+    display: QuLogDateDisplay,
+    theme: QuLogHtmlTheme,
+}
+
+/// Context the html template is rendered with, values are escaped by tinytemplate
+/// unless the template asks for `| unescaped`.
+#[derive(Serialize)]
+struct HtmlContext<'a> {
+    style: &'a str,
+    days: Vec<HtmlDay>,
+}
+
+/// Logs of one calendar day of the display time zone, in the order they were fetched.
+#[derive(Serialize)]
+struct HtmlDay {
+    date: String,
+    logs: Vec<HtmlLog>,
+}
+
+#[derive(Serialize)]
+struct HtmlLog {
+    id: Option<i64>,
+    date: String,
+    level: QuLogLevel,
+    source: Option<String>,
+    text: String,
+    tags: Vec<String>,
+    fields: Vec<HtmlField>,
+    attachments: Vec<HtmlAttachment>,
+    edited: bool,
+}
+
+/// An attachment, `href` is where the export command writes it, relative to the export.
+#[derive(Serialize)]
+struct HtmlAttachment {
+    name: String,
+    href: String,
+    image: bool,
+}
+
+#[derive(Serialize)]
+struct HtmlField {
+    key: String,
+    value: String,
+}
+
+impl QuLogExporter for HtmlExporter {
+    fn extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn export(&self, logs: &[QuLog]) -> Result<String, String> {
+        let mut days: Vec<HtmlDay> = vec![];
+        let mut current_day = None;
+
+        for log in logs {
+            let day = self.display.convert(log.create_date).date_naive();
+            if current_day != Some(day) {
+                current_day = Some(day);
+                days.push(HtmlDay {
+                    date: day.format("%A, %Y-%m-%d").to_string(),
+                    logs: vec![],
+                });
+            }
+
+            days.last_mut().unwrap().logs.push(HtmlLog {
+                id: log.id,
+                date: self.display.format(log.create_date),
+                level: log.level,
+                source: log.source.clone(),
+                text: log.text.clone(),
+                tags: log.tags.0.clone(),
+                fields: log
+                    .fields
+                    .iter()
+                    .map(|(key, value)| HtmlField {
+                        key: key.clone(),
+                        value: value.clone(),
+                    })
+                    .collect(),
+                attachments: log
+                    .attachments
+                    .iter()
+                    .map(|attachment| HtmlAttachment {
+                        name: attachment.name.clone(),
+                        href: format!("{}/{}", attachment::EXPORT_DIR, attachment.file_name()),
+                        image: attachment.is_image(),
+                    })
+                    .collect(),
+                edited: log.updated_date.is_some(),
+            });
+        }
+
+        let mut template = TinyTemplate::new();
+        template
+            .add_template("export", &self.theme.template)
+            .map_err(|err| format!("Invalid html template: {}", err))?;
+
+        let context = HtmlContext {
+            style: &self.theme.style,
+            days,
+        };
+        template
+            .render("export", &context)
+            .map_err(|err| format!("Unable to render html template: {}", err))
+    }
+}
+
+struct CsvExporter;
+
+impl QuLogExporter for CsvExporter {
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn export(&self, logs: &[QuLog]) -> Result<String, String> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        for log in logs {
+            writer
+                .serialize(QuLogCsvRecord::from(QuLogRecord::from(log)))
+                .map_err(|err| format!("Unable to write csv: {}", err))?;
+        }
+
+        let bytes = writer
+            .into_inner()
+            .map_err(|err| format!("Unable to write csv: {}", err))?;
+        String::from_utf8(bytes).map_err(|err| format!("Unable to write csv: {}", err))
+    }
+}
+
+struct JsonExporter;
+
+impl QuLogExporter for JsonExporter {
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn export(&self, logs: &[QuLog]) -> Result<String, String> {
+        let records: Vec<QuLogRecord> = logs.iter().map(QuLogRecord::from).collect();
+        serde_json::to_string_pretty(&records)
+            .map_err(|err| format!("Unable to write json: {}", err))
+    }
+}
+
+/// One JSON object per line, handy for `jq` and streaming tools.
+struct NdjsonExporter;
+
+impl QuLogExporter for NdjsonExporter {
+    fn extension(&self) -> &'static str {
+        "ndjson"
+    }
+
+    fn export(&self, logs: &[QuLog]) -> Result<String, String> {
+        let mut lines = String::new();
+        for log in logs {
+            let line = serde_json::to_string(&QuLogRecord::from(log))
+                .map_err(|err| format!("Unable to write json: {}", err))?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+
+        Ok(lines)
+    }
+}
+
+struct MarkdownExporter {
+    display: QuLogDateDisplay,
+}
+
+impl QuLogExporter for MarkdownExporter {
+    fn extension(&self) -> &'static str {
+        "md"
+    }
+
+    fn export(&self, logs: &[QuLog]) -> Result<String, String> {
+        let mut table = String::from(
+            "| Date | Level | Source | Log | Tags | Fields |\n| --- | --- | --- | --- | --- | --- |\n",
+        );
+
+        for log in logs {
+            let fields: Vec<String> = log
+                .fields
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            table.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} |\n",
+                escape_markdown(&self.display.format(log.create_date)),
+                log.level,
+                escape_markdown(log.source.as_deref().unwrap_or_default()),
+                escape_markdown(&log.text),
+                escape_markdown(&log.tags.0.join(", ")),
+                escape_markdown(&fields.join(", "))
+            ));
+        }
+
+        Ok(table)
+    }
+}
+
+/// Keeps a value inside its table cell: pipes and backslashes are escaped,
+/// `&`, `<` and `>` become entities so no raw html gets through and line breaks become `<br>`.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '|' => escaped.push_str("\\|"),
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\r' => {}
+            '\n' => escaped.push_str("<br>"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone, Utc};
+
+    use super::*;
+    use crate::quick_logger::{dates::QuLogTimeZone, QuLogTags};
+
+    const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+    fn display() -> QuLogDateDisplay {
+        QuLogDateDisplay {
+            format: DATE_FORMAT.to_string(),
+            tz: QuLogTimeZone::Local,
+        }
+    }
+
+    fn logs() -> Vec<QuLog> {
+        let date = Local
+            .with_ymd_and_hms(2024, 3, 1, 9, 30, 0)
+            .unwrap()
+            .fixed_offset();
+        vec![
+            QuLog {
+                id: Some(1),
+                text: "plain log".to_string(),
+                tags: QuLogTags::from("work,rust".to_string()),
+                level: QuLogLevel::default(),
+                source: None,
+                fields: BTreeMap::new(),
+                attachments: Vec::new(),
+                create_date: date,
+                updated_date: None,
+            },
+            QuLog {
+                id: Some(2),
+                text: "a \"quoted\", | piped\nmulti-line <b>log</b> & \\ more".to_string(),
+                tags: QuLogTags::empty(),
+                level: QuLogLevel::Warn,
+                source: Some("deploy.sh".to_string()),
+                fields: BTreeMap::from([
+                    ("env".to_string(), "prod".to_string()),
+                    ("note".to_string(), "a | b, \"c\"".to_string()),
+                ]),
+                attachments: Vec::new(),
+                create_date: date,
+                updated_date: Some(date.to_utc()),
+            },
+        ]
+    }
+
+    fn parse_date(date: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(date).unwrap()
+    }
+
+    fn from_record(record: QuLogRecord) -> QuLog {
+        QuLog {
+            id: record.id,
+            text: record.text,
+            tags: QuLogTags(record.tags),
+            level: record.level,
+            source: record.source,
+            fields: record.fields,
+            attachments: Vec::new(),
+            create_date: parse_date(&record.create_date),
+            updated_date: record
+                .updated_date
+                .as_deref()
+                .map(|date| parse_date(date).to_utc()),
+        }
+    }
+
+    fn assert_same_logs(actual: &[QuLog], expected: &[QuLog]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert_eq!(actual.id, expected.id);
+            assert_eq!(actual.text, expected.text);
+            assert_eq!(actual.tags.0, expected.tags.0);
+            assert_eq!(actual.level, expected.level);
+            assert_eq!(actual.source, expected.source);
+            assert_eq!(actual.fields, expected.fields);
+            assert_eq!(actual.create_date, expected.create_date);
+            assert_eq!(actual.updated_date, expected.updated_date);
+        }
+    }
+
+    /// Splits a markdown table row on unescaped pipes and undoes `escape_markdown`.
+    fn parse_markdown_row(row: &str) -> Vec<String> {
+        let mut cells = vec![];
+        let mut cell = String::new();
+        let mut chars = row.trim().trim_start_matches('|').chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => cell.push(chars.next().unwrap()),
+                '|' => cells.push(
+                    std::mem::take(&mut cell)
+                        .trim()
+                        .replace("<br>", "\n")
+                        .replace("&lt;", "<")
+                        .replace("&gt;", ">")
+                        .replace("&amp;", "&"),
+                ),
+                c => cell.push(c),
+            }
+        }
+        cells
+    }
+
+    fn parse_local(date: &str) -> DateTime<FixedOffset> {
+        let date = NaiveDateTime::parse_from_str(date, DATE_FORMAT).unwrap();
+        Local.from_local_datetime(&date).unwrap().fixed_offset()
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let logs = logs();
+        let exported = QuLogExportFormat::Csv
+            .exporter(&display(), QuLogHtmlTheme::default())
+            .export(&logs)
+            .unwrap();
+
+        let imported: Vec<QuLog> = csv::Reader::from_reader(exported.as_bytes())
+            .deserialize::<QuLogCsvRecord>()
+            .map(|record| from_record(QuLogRecord::try_from(record.unwrap()).unwrap()))
+            .collect();
+
+        assert_same_logs(&imported, &logs);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let logs = logs();
+        let exported = QuLogExportFormat::Json
+            .exporter(&display(), QuLogHtmlTheme::default())
+            .export(&logs)
+            .unwrap();
+
+        let records: Vec<QuLogRecord> = serde_json::from_str(&exported).unwrap();
+        let imported: Vec<QuLog> = records.into_iter().map(from_record).collect();
+
+        assert_same_logs(&imported, &logs);
+    }
+
+    #[test]
+    fn test_ndjson_round_trip() {
+        let logs = logs();
+        let exported = QuLogExportFormat::Ndjson
+            .exporter(&display(), QuLogHtmlTheme::default())
+            .export(&logs)
+            .unwrap();
+
+        assert_eq!(exported.lines().count(), logs.len());
+        let imported: Vec<QuLog> = exported
+            .lines()
+            .map(|line| from_record(serde_json::from_str(line).unwrap()))
+            .collect();
+
+        assert_same_logs(&imported, &logs);
+    }
+
+    #[test]
+    fn test_markdown_round_trip() {
+        let logs = logs();
+        let exported = QuLogExportFormat::Markdown
+            .exporter(&display(), QuLogHtmlTheme::default())
+            .export(&logs)
+            .unwrap();
+
+        let rows: Vec<Vec<String>> = exported.lines().skip(2).map(parse_markdown_row).collect();
+        assert_eq!(rows.len(), logs.len());
+        for (row, log) in rows.iter().zip(&logs) {
+            assert_eq!(row.len(), 6);
+            assert_eq!(parse_local(&row[0]), log.create_date);
+            assert_eq!(row[1].parse(), Ok(log.level));
+            assert_eq!(row[2], log.source.clone().unwrap_or_default());
+            assert_eq!(row[3], log.text);
+            assert_eq!(QuLogTags::from(row[4].clone()).0, log.tags.0);
+        }
+        assert_eq!(rows[1][5], "env=prod, note=a | b, \"c\"");
+    }
+
+    #[test]
+    fn test_markdown_escapes_html() {
+        assert_eq!(
+            escape_markdown("<img src=x onerror=alert(1)>\nfish & chips"),
+            "&lt;img src=x onerror=alert(1)&gt;<br>fish &amp; chips"
+        );
+    }
+
+    #[test]
+    fn test_html_escapes_and_closes_rows() {
+        let logs = logs();
+        let theme = QuLogHtmlTheme {
+            style: "td > span { color: red; }".to_string(),
+            ..Default::default()
+        };
+        let exported = QuLogExportFormat::Html
+            .exporter(&display(), theme)
+            .export(&logs)
+            .unwrap();
+
+        assert!(exported.contains("<style>td > span { color: red; }</style>"));
+        assert!(!exported.contains("<b>log</b>"));
+        assert!(exported.contains("&lt;b&gt;log&lt;/b&gt; &amp; \\ more"));
+        assert_eq!(exported.matches("<tr>").count(), 2 + 1);
+        assert_eq!(exported.matches("</tr>").count(), 2 + 1);
+        assert!(exported.contains(r#"<span class="tag">work</span><span class="tag">rust</span>"#));
+        assert_eq!(exported.matches("(edited)").count(), 1);
+        assert!(exported.contains(r#"<td class="level warn">warn</td>"#));
+        assert!(exported.contains("<td>deploy.sh</td>"));
+        assert!(exported.contains(r#"<span class="field">note=a | b, &quot;c&quot;</span>"#));
+        // Line breaks are kept by the default template, even without a stylesheet
+        assert!(exported.contains("<td class=\"log\" style=\"white-space:pre-wrap\">a &quot;quoted&quot;, | piped\nmulti-line"));
+    }
+
+    #[test]
+    fn test_html_links_attachments() {
+        let mut logs = logs();
+        logs[0].attachments = vec![
+            attachment::QuLogAttachment {
+                id: 4,
+                name: "screen shot.PNG".to_string(),
+                size: 2048,
+            },
+            attachment::QuLogAttachment {
+                id: 5,
+                name: "<build>.log".to_string(),
+                size: 12,
+            },
+        ];
+
+        let exported = QuLogExportFormat::Html
+            .exporter(&display(), QuLogHtmlTheme::default())
+            .export(&logs)
+            .unwrap();
+
+        assert!(exported.contains(
+            r#"<a class="attachment" href="attachments/4-screen_shot.PNG"><img src="attachments/4-screen_shot.PNG" alt="screen shot.PNG"></a>"#
+        ));
+        assert!(exported.contains(
+            r#"<a class="attachment" href="attachments/5-_build_.log">&lt;build&gt;.log</a>"#
+        ));
+    }
+
+    #[test]
+    fn test_html_groups_by_day() {
+        let mut logs = logs();
+        logs[1].create_date = Local
+            .with_ymd_and_hms(2024, 3, 2, 8, 0, 0)
+            .unwrap()
+            .fixed_offset();
+        logs.push(QuLog {
+            id: Some(3),
+            text: "same day".to_string(),
+            tags: QuLogTags::empty(),
+            level: QuLogLevel::default(),
+            source: None,
+            fields: BTreeMap::new(),
+            attachments: Vec::new(),
+            create_date: Local
+                .with_ymd_and_hms(2024, 3, 2, 18, 0, 0)
+                .unwrap()
+                .fixed_offset(),
+            updated_date: None,
+        });
+
+        let exported = QuLogExportFormat::Html
+            .exporter(&display(), QuLogHtmlTheme::default())
+            .export(&logs)
+            .unwrap();
+
+        let days: Vec<&str> = exported.split(r#"<section class="day">"#).skip(1).collect();
+        assert_eq!(days.len(), 2);
+        assert!(days[0].contains("<h2>Friday, 2024-03-01</h2>"));
+        assert!(days[0].contains("plain log"));
+        assert!(days[1].contains("<h2>Saturday, 2024-03-02</h2>"));
+        assert!(days[1].contains("same day"));
+        assert_eq!(days[1].matches("<tr>").count(), 2 + 1);
+    }
+
+    #[test]
+    fn test_html_days_follow_display_time_zone() {
+        let mut logs = logs();
+        // Written late in the evening in New York, already the next day in UTC
+        let new_york = FixedOffset::west_opt(5 * 3600).unwrap();
+        logs[0].create_date = new_york.with_ymd_and_hms(2024, 3, 1, 22, 0, 0).unwrap();
+        logs.truncate(1);
+
+        let export = |tz| {
+            let display = QuLogDateDisplay { tz, ..display() };
+            QuLogExportFormat::Html
+                .exporter(&display, QuLogHtmlTheme::default())
+                .export(&logs)
+                .unwrap()
+        };
+
+        let utc = export(QuLogTimeZone::Utc);
+        assert!(utc.contains("<h2>Saturday, 2024-03-02</h2>"));
+        assert!(utc.contains("<td>2024-03-02 03:00:00</td>"));
+
+        let original = export(QuLogTimeZone::Original);
+        assert!(original.contains("<h2>Friday, 2024-03-01</h2>"));
+        assert!(original.contains("<td>2024-03-01 22:00:00</td>"));
+
+        let tokyo = export(QuLogTimeZone::Named(chrono_tz::Asia::Tokyo));
+        assert!(tokyo.contains("<td>2024-03-02 12:00:00</td>"));
+        assert_eq!(
+            logs[0].create_date.to_utc(),
+            Utc.with_ymd_and_hms(2024, 3, 2, 3, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_html_custom_template() {
+        let theme = QuLogHtmlTheme {
+            template: "{{ for day in days }}{{ for log in day.logs }}{log.id}:{log.text};{{ endfor }}{{ endfor }}"
+                .to_string(),
+            style: String::new(),
+        };
+        let exported = QuLogExportFormat::Html
+            .exporter(&display(), theme)
+            .export(&logs()[..1])
+            .unwrap();
+        assert_eq!(exported, "1:plain log;");
+
+        let theme = QuLogHtmlTheme {
+            template: "{{ for day in days }}".to_string(),
+            style: String::new(),
+        };
+        assert!(QuLogExportFormat::Html
+            .exporter(&display(), theme)
+            .export(&logs())
+            .is_err());
+    }
+}
@@ -0,0 +1,522 @@
+use std::{fmt::Display, path::Path};
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime};
+use clap::ValueEnum;
+use sqlx::{sqlite::SqlitePool, Acquire};
+
+use super::{
+    export::{QuLogCsvRecord, QuLogRecord},
+    insert_log, QuLog, QuLogTags,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub(super) enum QuLogImportFormat {
+    Csv,
+    Json,
+    Ndjson,
+    /// Plain-text export of jrnl, `[2024-03-01 09:30] Title` followed by the body
+    Jrnl,
+}
+
+impl QuLogImportFormat {
+    /// Format matching the extension of `path`, used when `--format` is not given.
+    pub(super) fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "csv" => Some(QuLogImportFormat::Csv),
+            "json" => Some(QuLogImportFormat::Json),
+            "ndjson" | "jsonl" => Some(QuLogImportFormat::Ndjson),
+            "jrnl" | "txt" => Some(QuLogImportFormat::Jrnl),
+            _ => None,
+        }
+    }
+
+    /// Parses every entry of `content`, an entry that can't be read becomes an error
+    /// without stopping the others. Fails only when the file as a whole can't be read.
+    pub(super) fn parse(self, content: &str) -> Result<Vec<QuLogImportRow>, String> {
+        match self {
+            QuLogImportFormat::Csv => parse_csv(content),
+            QuLogImportFormat::Json => parse_json(content),
+            QuLogImportFormat::Ndjson => Ok(parse_ndjson(content)),
+            QuLogImportFormat::Jrnl => Ok(parse_jrnl(content)),
+        }
+    }
+}
+
+/// Entry of an imported file, `row` is its line, or its position for json arrays.
+pub(super) struct QuLogImportRow {
+    row: usize,
+    log: Result<QuLog, String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) struct QuLogImportError {
+    pub(super) row: usize,
+    pub(super) message: String,
+}
+
+impl Display for QuLogImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "row {}: {}", self.row, self.message)
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct QuLogImportReport {
+    pub(super) imported: usize,
+    pub(super) duplicates: usize,
+    pub(super) errors: Vec<QuLogImportError>,
+}
+
+/// Saves `rows` in a single transaction. Logs with the same text and create date as an
+/// existing one are skipped, failing rows are reported and don't roll back the others.
+pub(super) async fn import_logs(
+    pool: &SqlitePool,
+    rows: Vec<QuLogImportRow>,
+) -> Result<QuLogImportReport, sqlx::Error> {
+    let mut report = QuLogImportReport::default();
+    let mut tx = pool.begin().await?;
+
+    for QuLogImportRow { row, log } in rows {
+        let log = match log {
+            Ok(log) => log,
+            Err(message) => {
+                report.errors.push(QuLogImportError { row, message });
+                continue;
+            }
+        };
+
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM qu_log WHERE text = $1 AND create_date = $2)",
+        )
+        .bind(&log.text)
+        .bind(log.create_date.to_utc())
+        .fetch_one(&mut *tx)
+        .await?;
+        if exists {
+            report.duplicates += 1;
+            continue;
+        }
+
+        let mut savepoint = tx.begin().await?;
+        match insert_log(&mut savepoint, &log).await {
+            Ok(_) => {
+                savepoint.commit().await?;
+                report.imported += 1;
+            }
+            Err(err) => {
+                savepoint.rollback().await?;
+                report.errors.push(QuLogImportError {
+                    row,
+                    message: err.to_string(),
+                });
+            }
+        }
+    }
+
+    tx.commit().await?;
+    Ok(report)
+}
+
+impl TryFrom<QuLogRecord> for QuLog {
+    type Error = String;
+
+    fn try_from(record: QuLogRecord) -> Result<Self, Self::Error> {
+        if record.text.trim().is_empty() {
+            return Err("text is empty".to_string());
+        }
+
+        Ok(QuLog {
+            id: None,
+            text: record.text,
+            tags: QuLogTags::from(record.tags.join(",")),
+            level: record.level,
+            source: record.source,
+            fields: record.fields,
+            attachments: Vec::new(),
+            create_date: parse_date(&record.create_date)?,
+            updated_date: record
+                .updated_date
+                .as_deref()
+                .map(|date| parse_date(date).map(|date| date.to_utc()))
+                .transpose()?,
+        })
+    }
+}
+
+/// Reads RFC 3339 dates as written by `export`, keeping their offset,
+/// or local `2024-03-01 09:30[:00]` dates.
+fn parse_date(date: &str) -> Result<DateTime<FixedOffset>, String> {
+    let date = date.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Ok(date);
+    }
+
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+        .and_then(|date| date.and_local_timezone(Local).earliest())
+        .map(|date| date.fixed_offset())
+        .ok_or(format!("invalid date {:?}", date))
+}
+
+fn parse_csv(content: &str) -> Result<Vec<QuLogImportRow>, String> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    reader
+        .headers()
+        .map_err(|err| format!("Invalid csv header: {}", err))?;
+
+    Ok(reader
+        .deserialize::<QuLogCsvRecord>()
+        .enumerate()
+        .map(|(index, record)| {
+            // Lines are only known for readable records, the header is line 1
+            let row = match &record {
+                Err(err) => err.position().map(|position| position.line() as usize),
+                Ok(_) => None,
+            };
+            QuLogImportRow {
+                row: row.unwrap_or(index + 2),
+                log: record
+                    .map_err(|err| err.to_string())
+                    .and_then(QuLogRecord::try_from)
+                    .and_then(QuLog::try_from),
+            }
+        })
+        .collect())
+}
+
+fn parse_json(content: &str) -> Result<Vec<QuLogImportRow>, String> {
+    let values: Vec<serde_json::Value> =
+        serde_json::from_str(content).map_err(|err| format!("Invalid json array: {}", err))?;
+
+    Ok(values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| QuLogImportRow {
+            row: index + 1,
+            log: serde_json::from_value::<QuLogRecord>(value)
+                .map_err(|err| err.to_string())
+                .and_then(QuLog::try_from),
+        })
+        .collect())
+}
+
+fn parse_ndjson(content: &str) -> Vec<QuLogImportRow> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| QuLogImportRow {
+            row: index + 1,
+            log: serde_json::from_str::<QuLogRecord>(line)
+                .map_err(|err| err.to_string())
+                .and_then(QuLog::try_from),
+        })
+        .collect()
+}
+
+/// Reads jrnl's plain-text export: every entry starts with a `2024-03-01 09:30` date,
+/// optionally in brackets and followed by a `*` star, then the title and the body lines.
+/// `@tags` of the entry become its tags.
+fn parse_jrnl(content: &str) -> Vec<QuLogImportRow> {
+    let mut rows = vec![];
+    let mut entry: Option<(usize, DateTime<FixedOffset>, Vec<&str>)> = None;
+
+    fn finish(
+        rows: &mut Vec<QuLogImportRow>,
+        entry: Option<(usize, DateTime<FixedOffset>, Vec<&str>)>,
+    ) {
+        if let Some((row, create_date, lines)) = entry {
+            let text = lines.join("\n").trim().to_string();
+            let log = if text.is_empty() {
+                Err("text is empty".to_string())
+            } else {
+                Ok(QuLog {
+                    tags: jrnl_tags(&text),
+                    create_date,
+                    ..QuLog::new(text, Vec::<String>::new())
+                })
+            };
+            rows.push(QuLogImportRow { row, log });
+        }
+    }
+
+    for (index, line) in content.lines().enumerate() {
+        match parse_jrnl_header(line) {
+            Some((create_date, title)) => {
+                finish(&mut rows, entry.take());
+                entry = Some((index + 1, create_date, vec![title]));
+            }
+            None => match &mut entry {
+                Some((_, _, lines)) => lines.push(line),
+                None if line.trim().is_empty() => {}
+                None => rows.push(QuLogImportRow {
+                    row: index + 1,
+                    log: Err("expected an entry starting with a date".to_string()),
+                }),
+            },
+        }
+    }
+    finish(&mut rows, entry);
+
+    rows
+}
+
+fn parse_jrnl_header(line: &str) -> Option<(DateTime<FixedOffset>, &str)> {
+    let (line, bracketed) = match line.strip_prefix('[') {
+        Some(line) => (line, true),
+        None => (line, false),
+    };
+
+    let date = line.get(..16)?;
+    let create_date = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M")
+        .ok()?
+        .and_local_timezone(Local)
+        .earliest()?
+        .fixed_offset();
+
+    let mut title = &line[16..];
+    if bracketed {
+        title = title.strip_prefix(']')?;
+    }
+    let title = title.trim_start();
+    let title = title.strip_prefix('*').unwrap_or(title).trim_start();
+
+    Some((create_date, title))
+}
+
+fn jrnl_tags(text: &str) -> QuLogTags {
+    let mut tags: Vec<String> = vec![];
+    for word in text.split_whitespace() {
+        if let Some(tag) = word.strip_prefix('@') {
+            let tag = tag.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '-' && c != '_');
+            if !tag.is_empty() && !tags.iter().any(|known| known == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+    }
+    QuLogTags(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::TimeZone;
+
+    use super::super::{
+        connect_to_db, create_log, dates::QuLogDateDisplay, export::QuLogExportFormat, fetch_logs,
+        migrations, DBConfig, QuLogFilter, QuLogLevel,
+    };
+    use super::*;
+
+    async fn pool_with(logs: &[(&str, DateTime<FixedOffset>, &str)]) -> SqlitePool {
+        let pool = connect_to_db(&DBConfig::in_memory()).await.unwrap();
+        migrations::migrate(&pool).await.unwrap();
+        for (text, create_date, tags) in logs {
+            let log = QuLog {
+                id: None,
+                text: text.to_string(),
+                tags: QuLogTags::from(tags.to_string()),
+                level: QuLogLevel::default(),
+                source: None,
+                fields: BTreeMap::new(),
+                attachments: Vec::new(),
+                create_date: *create_date,
+                updated_date: None,
+            };
+            create_log(&log, &pool).await.unwrap();
+        }
+        pool
+    }
+
+    fn date(day: u32, hour: u32) -> DateTime<FixedOffset> {
+        Local
+            .with_ymd_and_hms(2024, 3, day, hour, 0, 0)
+            .unwrap()
+            .fixed_offset()
+    }
+
+    fn logs(rows: &[QuLogImportRow]) -> Vec<&QuLog> {
+        rows.iter()
+            .filter_map(|row| row.log.as_ref().ok())
+            .collect()
+    }
+
+    fn errors(rows: &[QuLogImportRow]) -> Vec<usize> {
+        rows.iter()
+            .filter(|row| row.log.is_err())
+            .map(|row| row.row)
+            .collect()
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            QuLogImportFormat::from_path(Path::new("logs.CSV")),
+            Some(QuLogImportFormat::Csv)
+        );
+        assert_eq!(
+            QuLogImportFormat::from_path(Path::new("a/logs.jsonl")),
+            Some(QuLogImportFormat::Ndjson)
+        );
+        assert_eq!(QuLogImportFormat::from_path(Path::new("logs")), None);
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let content = "text,tags,create_date\n\
+            first,\"a, b\",2024-03-01 10:00\n\
+            second,,not a date\n\
+            \"multi\nline\",,2024-03-02T11:00:00+00:00\n";
+
+        let rows = QuLogImportFormat::Csv.parse(content).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(errors(&rows), vec![3]);
+
+        let logs = logs(&rows);
+        assert_eq!(logs[0].text, "first");
+        assert_eq!(logs[0].tags.0, vec!["a", "b"]);
+        assert_eq!(logs[0].create_date, date(1, 10));
+        assert_eq!(logs[1].text, "multi\nline");
+        assert_eq!(
+            logs[1].create_date,
+            DateTime::parse_from_rfc3339("2024-03-02T11:00:00+00:00").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_json_and_ndjson() {
+        let content = r#"[
+            {"text": "first", "tags": ["a"], "create_date": "2024-03-01 10:00:00"},
+            {"text": "", "create_date": "2024-03-01 10:00:00"},
+            {"tags": []}
+        ]"#;
+        let rows = QuLogImportFormat::Json.parse(content).unwrap();
+        assert_eq!(errors(&rows), vec![2, 3]);
+        assert_eq!(logs(&rows)[0].tags.0, vec!["a"]);
+
+        assert!(QuLogImportFormat::Json.parse("{}").is_err());
+
+        let content = "{\"text\": \"first\", \"create_date\": \"2024-03-01 10:00\"}\n\
+            \n\
+            {broken\n";
+        let rows = QuLogImportFormat::Ndjson.parse(content).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(errors(&rows), vec![3]);
+        assert_eq!(logs(&rows)[0].create_date, date(1, 10));
+    }
+
+    #[test]
+    fn test_parse_jrnl() {
+        let content = "\
+[2024-03-01 10:00] Standup with @team. Talked about @rust.
+Second line of the body.
+
+2024-03-02 11:00 * Starred entry @team
+
+[2024-03-03 12:00]
+";
+        let rows = QuLogImportFormat::Jrnl.parse(content).unwrap();
+        assert_eq!(errors(&rows), vec![6]);
+
+        let logs = logs(&rows);
+        assert_eq!(logs.len(), 2);
+        assert_eq!(
+            logs[0].text,
+            "Standup with @team. Talked about @rust.\nSecond line of the body."
+        );
+        assert_eq!(logs[0].tags.0, vec!["team", "rust"]);
+        assert_eq!(logs[0].create_date, date(1, 10));
+        assert_eq!(logs[1].text, "Starred entry @team");
+        assert_eq!(logs[1].create_date, date(2, 11));
+
+        let rows = QuLogImportFormat::Jrnl.parse("no date here\n").unwrap();
+        assert_eq!(errors(&rows), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn test_import_skips_duplicates() -> Result<(), sqlx::Error> {
+        let pool = pool_with(&[("existing", date(1, 10), "a")]).await;
+
+        let content = "text,tags,create_date\n\
+            existing,a,2024-03-01 10:00\n\
+            new,b,2024-03-01 11:00\n\
+            new,b,2024-03-01 11:00\n\
+            broken,b,yesterday\n";
+        let rows = QuLogImportFormat::Csv.parse(content).unwrap();
+        let report = import_logs(&pool, rows).await?;
+
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.duplicates, 2);
+        assert_eq!(
+            report.errors,
+            vec![QuLogImportError {
+                row: 5,
+                message: "invalid date \"yesterday\"".to_string()
+            }]
+        );
+
+        let logs = fetch_logs(&pool, &QuLogFilter::default()).await?;
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[1].text, "new");
+        assert_eq!(logs[1].tags.0, vec!["b"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() -> Result<(), sqlx::Error> {
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        let pool = pool_with(&[
+            ("first", date(1, 10), "a,b"),
+            ("second, \"quoted\"", date(2, 10), ""),
+            (
+                "in tokyo",
+                tokyo.with_ymd_and_hms(2024, 3, 3, 8, 0, 0).unwrap(),
+                "",
+            ),
+        ])
+        .await;
+        let logs = fetch_logs(&pool, &QuLogFilter::default()).await?;
+
+        for format in [
+            QuLogImportFormat::Csv,
+            QuLogImportFormat::Json,
+            QuLogImportFormat::Ndjson,
+        ] {
+            let export_format = match format {
+                QuLogImportFormat::Csv => QuLogExportFormat::Csv,
+                QuLogImportFormat::Json => QuLogExportFormat::Json,
+                _ => QuLogExportFormat::Ndjson,
+            };
+            let exported = export_format
+                .exporter(
+                    &QuLogDateDisplay {
+                        format: "%Y".to_string(),
+                        tz: Default::default(),
+                    },
+                    Default::default(),
+                )
+                .export(&logs)
+                .unwrap();
+
+            let other = pool_with(&[]).await;
+            let report = import_logs(&other, format.parse(&exported).unwrap()).await?;
+            assert_eq!(report.imported, 3);
+
+            let imported = fetch_logs(&other, &QuLogFilter::default()).await?;
+            for (imported, log) in imported.iter().zip(&logs) {
+                assert_eq!(imported.text, log.text);
+                assert_eq!(imported.tags.0, log.tags.0);
+                assert_eq!(imported.create_date, log.create_date);
+                assert_eq!(imported.create_date.offset(), log.create_date.offset());
+            }
+
+            // Importing the same file again only finds duplicates
+            let report = import_logs(&other, format.parse(&exported).unwrap()).await?;
+            assert_eq!(report.imported, 0);
+            assert_eq!(report.duplicates, 3);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    fs::OpenOptions,
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicU32, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::error::QuLogError;
+
+/// Text of `qulog log`: the argument itself, stdin for `-`, or what was written
+/// in the editor with `--edit`, the argument being the draft it starts from.
+pub(super) fn read_log_text(
+    text: Option<String>,
+    edit: bool,
+    stdin: impl Read,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, QuLogError> {
+    let text = match (text.as_deref(), edit) {
+        (Some("-"), true) => {
+            return Err(QuLogError::Validation(
+                "--edit can't read the log from stdin, the editor needs it".to_string(),
+            ))
+        }
+        (Some("-"), false) => read_stdin(stdin)?,
+        (_, true) => {
+            let path = create_draft(&std::env::temp_dir(), text.as_deref().unwrap_or_default())?;
+            let written = run_editor(&editor(env), &path);
+            let _ = std::fs::remove_file(&path);
+            written?
+        }
+        (Some(_), false) => text.unwrap_or_default(),
+        (None, false) => {
+            return Err(QuLogError::Validation(
+                "The text of the log is missing, pass it, - to read stdin or --edit".to_string(),
+            ))
+        }
+    };
+
+    clean(&text)
+}
+
+fn read_stdin(mut stdin: impl Read) -> Result<String, QuLogError> {
+    let mut text = String::new();
+    stdin
+        .read_to_string(&mut text)
+        .map_err(|err| QuLogError::io("Unable to read the log from stdin", err))?;
+    Ok(text)
+}
+
+/// `$VISUAL`, `$EDITOR` or vi, like git picks it.
+fn editor(env: &dyn Fn(&str) -> Option<String>) -> String {
+    ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(env)
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or("vi".to_string())
+}
+
+/// Writes `draft` to a new file in `dir`, only readable by the user. The file is created
+/// exclusively under a name nobody can count on, another one is picked when it exists, so
+/// a file or symlink left in a shared temp folder is never written through.
+fn create_draft(dir: &Path, draft: &str) -> Result<PathBuf, QuLogError> {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    for _ in 0..100 {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.subsec_nanos());
+        let path = dir.join(format!(
+            "qulog-{}-{}-{:x}.txt",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            nanos
+        ));
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut file = match options.open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => {
+                return Err(QuLogError::io(
+                    format!("Unable to create {}", path.display()),
+                    err,
+                ))
+            }
+        };
+        file.write_all(draft.as_bytes())
+            .map_err(|err| QuLogError::io(format!("Unable to write {}", path.display()), err))?;
+        return Ok(path);
+    }
+
+    Err(QuLogError::io(
+        format!("Unable to create a draft in {}", dir.display()),
+        ErrorKind::AlreadyExists.into(),
+    ))
+}
+
+/// Opens `editor` on the draft at `path` and reads the file back once it exits.
+/// The editor may come with arguments, like `code --wait`.
+fn run_editor(editor: &str, path: &Path) -> Result<String, QuLogError> {
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|err| QuLogError::io(format!("Unable to run the editor {}", editor), err))?;
+    if !status.success() {
+        return Err(QuLogError::Validation(format!(
+            "The editor {} failed with {}, no log is saved",
+            editor, status
+        )));
+    }
+
+    std::fs::read_to_string(path)
+        .map_err(|err| QuLogError::io(format!("Unable to read {}", path.display()), err))
+}
+
+/// Windows line breaks become `\n` and the blank lines around the text are dropped,
+/// like the newline ending piped output.
+fn clean(text: &str) -> Result<String, QuLogError> {
+    let text = text.replace("\r\n", "\n");
+    let text = text.trim_end().trim_start_matches('\n');
+    if text.trim().is_empty() {
+        return Err(QuLogError::Validation(
+            "The log is empty, no log is saved".to_string(),
+        ));
+    }
+    Ok(text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn test_read_log_text() {
+        let stdin = "\nfirst line\r\n  indented second line\n\n".as_bytes();
+        assert_eq!(
+            read_log_text(Some("-".to_string()), false, stdin, &no_env).unwrap(),
+            "first line\n  indented second line"
+        );
+        assert_eq!(
+            read_log_text(
+                Some("as is".to_string()),
+                false,
+                "unused".as_bytes(),
+                &no_env
+            )
+            .unwrap(),
+            "as is"
+        );
+
+        let empty = read_log_text(Some("-".to_string()), false, " \n\n".as_bytes(), &no_env);
+        assert!(matches!(empty, Err(QuLogError::Validation(_))));
+        let missing = read_log_text(None, false, "".as_bytes(), &no_env);
+        assert!(matches!(missing, Err(QuLogError::Validation(_))));
+        let both = read_log_text(Some("-".to_string()), true, "".as_bytes(), &no_env);
+        assert!(matches!(both, Err(QuLogError::Validation(_))));
+    }
+
+    #[test]
+    fn test_editor() {
+        let env = |key: &str| match key {
+            "VISUAL" => Some(" ".to_string()),
+            "EDITOR" => Some("nano -w".to_string()),
+            _ => None,
+        };
+        assert_eq!(editor(&env), "nano -w");
+        assert_eq!(editor(&no_env), "vi");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_log_text_from_editor() {
+        // An "editor" appending a second paragraph to the draft
+        let dir = std::env::temp_dir().join(format!("qulog-editor-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("append.sh");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\nprintf '\\n\\nsecond paragraph\\n' >> \"$2\"\n",
+        )
+        .unwrap();
+
+        let editor = format!("sh {} --ignored", script.display());
+        let env = |key: &str| (key == "EDITOR").then(|| editor.clone());
+        let text = read_log_text(Some("draft".to_string()), true, "".as_bytes(), &env).unwrap();
+        assert_eq!(text, "draft\n\nsecond paragraph");
+
+        let env = |key: &str| (key == "EDITOR").then(|| "false".to_string());
+        let failed = read_log_text(None, true, "".as_bytes(), &env);
+        assert!(matches!(failed, Err(QuLogError::Validation(_))));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_create_draft() {
+        let dir = std::env::temp_dir().join(format!("qulog-draft-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let first = create_draft(&dir, "draft").unwrap();
+        let second = create_draft(&dir, "other").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "draft");
+        assert_eq!(std::fs::read_to_string(&second).unwrap(), "other");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&first).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
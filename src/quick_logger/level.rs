@@ -0,0 +1,154 @@
+use std::{fmt::Display, str::FromStr};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// How important a log is, logs written without `--level` are `info`.
+#[derive(
+    Debug,
+    Default,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    ValueEnum,
+    Serialize,
+    Deserialize,
+    sqlx::Type,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum QuLogLevel {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl QuLogLevel {
+    pub const ALL: [QuLogLevel; 4] = [
+        QuLogLevel::Debug,
+        QuLogLevel::Info,
+        QuLogLevel::Warn,
+        QuLogLevel::Error,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuLogLevel::Debug => "debug",
+            QuLogLevel::Info => "info",
+            QuLogLevel::Warn => "warn",
+            QuLogLevel::Error => "error",
+        }
+    }
+}
+
+impl Display for QuLogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for QuLogLevel {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        QuLogLevel::ALL
+            .into_iter()
+            .find(|level| level.as_str().eq_ignore_ascii_case(value.trim()))
+            .ok_or(format!(
+                "invalid level {:?}, expected debug, info, warn or error",
+                value
+            ))
+    }
+}
+
+/// Levels selected by `show --level`, written `warn`, `>=warn`, `>warn`, `<=info` or `<info`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct QuLogLevelFilter(pub(super) Vec<QuLogLevel>);
+
+impl FromStr for QuLogLevelFilter {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let (matches, level): (fn(&QuLogLevel, &QuLogLevel) -> bool, &str) =
+            if let Some(level) = value.strip_prefix(">=") {
+                (|log, level| log >= level, level)
+            } else if let Some(level) = value.strip_prefix("<=") {
+                (|log, level| log <= level, level)
+            } else if let Some(level) = value.strip_prefix('>') {
+                (|log, level| log > level, level)
+            } else if let Some(level) = value.strip_prefix('<') {
+                (|log, level| log < level, level)
+            } else {
+                (
+                    |log, level| log == level,
+                    value.strip_prefix('=').unwrap_or(value),
+                )
+            };
+
+        let level: QuLogLevel = level.parse()?;
+        let levels: Vec<QuLogLevel> = QuLogLevel::ALL
+            .into_iter()
+            .filter(|log| matches(log, &level))
+            .collect();
+        if levels.is_empty() {
+            return Err(format!("no level is {}", value));
+        }
+        Ok(QuLogLevelFilter(levels))
+    }
+}
+
+/// Parses a `key=value` pair of `--field`, the value may be empty but the key may not.
+pub(super) fn parse_field(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("invalid field {:?}, expected key=value", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use QuLogLevel::*;
+
+    #[test]
+    fn test_level_filter_parsing() {
+        let levels = |value: &str| value.parse::<QuLogLevelFilter>().map(|filter| filter.0);
+
+        assert_eq!(levels("warn"), Ok(vec![Warn]));
+        assert_eq!(levels("=WARN"), Ok(vec![Warn]));
+        assert_eq!(levels(">=warn"), Ok(vec![Warn, Error]));
+        assert_eq!(levels(">warn"), Ok(vec![Error]));
+        assert_eq!(levels("<=info"), Ok(vec![Debug, Info]));
+        assert_eq!(levels("<info"), Ok(vec![Debug]));
+        assert!(levels(">error").is_err());
+        assert!(levels(">=warning").is_err());
+        assert!(levels("").is_err());
+    }
+
+    #[test]
+    fn test_field_parsing() {
+        assert_eq!(
+            parse_field("env=prod"),
+            Ok(("env".to_string(), "prod".to_string()))
+        );
+        assert_eq!(
+            parse_field("query=a=b"),
+            Ok(("query".to_string(), "a=b".to_string()))
+        );
+        assert_eq!(
+            parse_field("empty="),
+            Ok(("empty".to_string(), String::new()))
+        );
+        assert!(parse_field("env").is_err());
+        assert!(parse_field("=prod").is_err());
+    }
+}
@@ -0,0 +1,394 @@
+use chrono::{DateTime, Local, Utc};
+use sqlx::{sqlite::SqlitePool, FromRow};
+
+/// One step of the qulog schema. Versions are applied in increasing order
+/// and recorded in the `schema_version` table.
+pub(super) struct Migration {
+    pub(super) version: i64,
+    pub(super) name: &'static str,
+    pub(super) sql: &'static str,
+}
+
+/// Every migration of the qulog database, oldest first.
+/// Applied migrations must never be edited, add a new one instead.
+pub(super) const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_qu_log",
+        // `IF NOT EXISTS`: databases created before migrations existed already have it.
+        sql: r#"
+        CREATE TABLE IF NOT EXISTS qu_log(
+            id  INTEGER PRIMARY KEY AUTOINCREMENT,
+            text TEXT NOT NULL,
+            tags TEXT DEFAULT '',
+            create_date DATETIME NOT NULL
+        );
+        "#,
+    },
+    Migration {
+        version: 2,
+        name: "create_tag_tables",
+        // Moves tags stored as a comma-joined string in `qu_log.tags` into the tag tables.
+        sql: r#"
+        CREATE TABLE IF NOT EXISTS tag(
+            id  INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE
+        );
+        CREATE TABLE IF NOT EXISTS qu_log_tag(
+            log_id INTEGER NOT NULL REFERENCES qu_log(id) ON DELETE CASCADE,
+            tag_id INTEGER NOT NULL REFERENCES tag(id) ON DELETE CASCADE,
+            PRIMARY KEY (log_id, tag_id)
+        );
+        CREATE INDEX IF NOT EXISTS qu_log_tag_tag_id ON qu_log_tag(tag_id);
+
+        WITH RECURSIVE split(log_id, name, rest) AS (
+            SELECT id, '', tags || ',' FROM qu_log WHERE tags IS NOT NULL AND tags != ''
+            UNION ALL
+            SELECT log_id,
+                trim(substr(rest, 1, instr(rest, ',') - 1)),
+                substr(rest, instr(rest, ',') + 1)
+            FROM split WHERE rest != ''
+        )
+        INSERT OR IGNORE INTO tag (name) SELECT DISTINCT name FROM split WHERE name != '';
+
+        WITH RECURSIVE split(log_id, name, rest) AS (
+            SELECT id, '', tags || ',' FROM qu_log WHERE tags IS NOT NULL AND tags != ''
+            UNION ALL
+            SELECT log_id,
+                trim(substr(rest, 1, instr(rest, ',') - 1)),
+                substr(rest, instr(rest, ',') + 1)
+            FROM split WHERE rest != ''
+        )
+        INSERT OR IGNORE INTO qu_log_tag (log_id, tag_id)
+        SELECT split.log_id, tag.id FROM split JOIN tag ON tag.name = split.name
+        ORDER BY split.log_id;
+
+        UPDATE qu_log SET tags = '' WHERE tags IS NOT NULL AND tags != '';
+        "#,
+    },
+    Migration {
+        version: 3,
+        name: "create_qu_log_fts",
+        // External content table over `qu_log.text`, kept in sync by triggers.
+        sql: r#"
+        CREATE VIRTUAL TABLE qu_log_fts USING fts5(
+            text,
+            content = 'qu_log',
+            content_rowid = 'id'
+        );
+
+        CREATE TRIGGER qu_log_fts_after_insert AFTER INSERT ON qu_log BEGIN
+            INSERT INTO qu_log_fts (rowid, text) VALUES (new.id, new.text);
+        END;
+
+        CREATE TRIGGER qu_log_fts_after_delete AFTER DELETE ON qu_log BEGIN
+            INSERT INTO qu_log_fts (qu_log_fts, rowid, text) VALUES ('delete', old.id, old.text);
+        END;
+
+        CREATE TRIGGER qu_log_fts_after_update AFTER UPDATE OF text ON qu_log BEGIN
+            INSERT INTO qu_log_fts (qu_log_fts, rowid, text) VALUES ('delete', old.id, old.text);
+            INSERT INTO qu_log_fts (rowid, text) VALUES (new.id, new.text);
+        END;
+
+        INSERT INTO qu_log_fts (qu_log_fts) VALUES ('rebuild');
+        "#,
+    },
+    Migration {
+        version: 4,
+        name: "create_qu_log_edit",
+        // Every edit keeps the text and tags from before and after it.
+        sql: r#"
+        ALTER TABLE qu_log ADD COLUMN updated_date DATETIME;
+
+        CREATE TABLE qu_log_edit(
+            id  INTEGER PRIMARY KEY AUTOINCREMENT,
+            log_id INTEGER NOT NULL REFERENCES qu_log(id) ON DELETE CASCADE,
+            old_text TEXT NOT NULL,
+            new_text TEXT NOT NULL,
+            old_tags TEXT NOT NULL,
+            new_tags TEXT NOT NULL,
+            edit_date DATETIME NOT NULL
+        );
+        CREATE INDEX qu_log_edit_log_id ON qu_log_edit(log_id);
+        "#,
+    },
+    Migration {
+        version: 5,
+        name: "store_dates_in_utc",
+        // Dates were written with the offset of the machine, which doesn't sort or compare
+        // as text once offsets differ. They become UTC, keeping the fractional seconds, and
+        // the offset the log was written with moves to create_offset (seconds east of UTC).
+        sql: r#"
+        ALTER TABLE qu_log ADD COLUMN create_offset INTEGER NOT NULL DEFAULT 0;
+
+        UPDATE qu_log SET
+            create_offset =
+                (CASE substr(create_date, -6, 1) WHEN '-' THEN -1 ELSE 1 END)
+                * (CAST(substr(create_date, -5, 2) AS INTEGER) * 3600
+                    + CAST(substr(create_date, -2, 2) AS INTEGER) * 60),
+            create_date =
+                strftime('%Y-%m-%dT%H:%M:%S', create_date)
+                || substr(create_date, 20, length(create_date) - 25)
+                || '+00:00'
+        WHERE substr(create_date, -6, 1) IN ('+', '-')
+            AND strftime('%Y-%m-%dT%H:%M:%S', create_date) IS NOT NULL;
+
+        UPDATE qu_log SET
+            updated_date =
+                strftime('%Y-%m-%dT%H:%M:%S', updated_date)
+                || substr(updated_date, 20, length(updated_date) - 25)
+                || '+00:00'
+        WHERE substr(updated_date, -6, 1) IN ('+', '-')
+            AND strftime('%Y-%m-%dT%H:%M:%S', updated_date) IS NOT NULL;
+
+        UPDATE qu_log_edit SET
+            edit_date =
+                strftime('%Y-%m-%dT%H:%M:%S', edit_date)
+                || substr(edit_date, 20, length(edit_date) - 25)
+                || '+00:00'
+        WHERE substr(edit_date, -6, 1) IN ('+', '-')
+            AND strftime('%Y-%m-%dT%H:%M:%S', edit_date) IS NOT NULL;
+
+        CREATE INDEX qu_log_create_date ON qu_log(create_date);
+        "#,
+    },
+    Migration {
+        version: 6,
+        name: "add_level_source_fields",
+        // Existing logs become `info` logs without a source or fields.
+        sql: r#"
+        ALTER TABLE qu_log ADD COLUMN level TEXT NOT NULL DEFAULT 'info';
+        ALTER TABLE qu_log ADD COLUMN source TEXT;
+        ALTER TABLE qu_log ADD COLUMN fields TEXT NOT NULL DEFAULT '{}';
+
+        CREATE INDEX qu_log_level ON qu_log(level);
+        "#,
+    },
+    Migration {
+        version: 7,
+        name: "create_qu_log_attachment",
+        // Files are kept in the database so it stays the only thing to back up.
+        sql: r#"
+        CREATE TABLE qu_log_attachment(
+            id  INTEGER PRIMARY KEY AUTOINCREMENT,
+            log_id INTEGER NOT NULL REFERENCES qu_log(id) ON DELETE CASCADE,
+            name TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            data BLOB NOT NULL
+        );
+        CREATE INDEX qu_log_attachment_log_id ON qu_log_attachment(log_id);
+        "#,
+    },
+];
+
+#[derive(Debug, FromRow)]
+pub(super) struct AppliedMigration {
+    pub(super) version: i64,
+    pub(super) name: String,
+    pub(super) applied_date: DateTime<Local>,
+}
+
+async fn create_schema_version_table_if_not_exists(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version(
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_date DATETIME NOT NULL
+        )
+    "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Migrations recorded in `schema_version`, without creating the table when it's missing.
+pub(super) async fn applied_migrations(
+    pool: &SqlitePool,
+) -> Result<Vec<AppliedMigration>, sqlx::Error> {
+    let has_table: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'",
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if !has_table {
+        return Ok(Vec::new());
+    }
+
+    sqlx::query_as::<_, AppliedMigration>(
+        "SELECT version, name, applied_date FROM schema_version ORDER BY version",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub(super) async fn pending_migrations(
+    pool: &SqlitePool,
+) -> Result<Vec<&'static Migration>, sqlx::Error> {
+    let current = applied_migrations(pool)
+        .await?
+        .iter()
+        .map(|m| m.version)
+        .max()
+        .unwrap_or(0);
+
+    Ok(MIGRATIONS.iter().filter(|m| m.version > current).collect())
+}
+
+/// Applies every pending migration, each one in its own transaction,
+/// and returns the ones that were applied.
+pub(super) async fn migrate(pool: &SqlitePool) -> Result<Vec<&'static Migration>, sqlx::Error> {
+    create_schema_version_table_if_not_exists(pool).await?;
+
+    let pending = pending_migrations(pool).await?;
+    for migration in &pending {
+        let mut tx = pool.begin().await?;
+
+        sqlx::query(migration.sql).execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO schema_version (version, name, applied_date) VALUES ($1, $2, $3)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+    }
+
+    Ok(pending)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{connect_to_db, DBConfig};
+    use super::*;
+
+    #[test]
+    fn test_migration_versions_are_ordered() {
+        let versions: Vec<i64> = MIGRATIONS.iter().map(|m| m.version).collect();
+        assert!(versions.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(versions.first(), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_migrate_applies_everything_once() {
+        let pool = connect_to_db(&DBConfig::in_memory()).await.unwrap();
+
+        assert_eq!(
+            pending_migrations(&pool).await.unwrap().len(),
+            MIGRATIONS.len()
+        );
+        assert!(applied_migrations(&pool).await.unwrap().is_empty());
+
+        let applied = migrate(&pool).await.unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert!(pending_migrations(&pool).await.unwrap().is_empty());
+
+        let applied = migrate(&pool).await.unwrap();
+        assert!(applied.is_empty());
+
+        let recorded = applied_migrations(&pool).await.unwrap();
+        assert_eq!(recorded.len(), MIGRATIONS.len());
+        assert_eq!(
+            recorded.last().unwrap().name,
+            MIGRATIONS.last().unwrap().name
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migrate_dates_to_utc() -> Result<(), sqlx::Error> {
+        let pool = connect_to_db(&DBConfig::in_memory()).await?;
+        create_schema_version_table_if_not_exists(&pool).await?;
+        for migration in MIGRATIONS.iter().filter(|m| m.version < 5) {
+            sqlx::query(migration.sql).execute(&pool).await?;
+            sqlx::query(
+                "INSERT INTO schema_version (version, name, applied_date) VALUES ($1, $2, $3)",
+            )
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(Utc::now())
+            .execute(&pool)
+            .await?;
+        }
+
+        // Dates as written with the offset of the machine, the new york log is the oldest
+        // one but came second when sorting the text.
+        for (text, create_date, updated_date) in [
+            ("berlin", "2024-03-31T01:30:00.250+01:00", None),
+            (
+                "new york",
+                "2024-03-30T19:00:00-05:00",
+                Some("2024-04-01T08:00:00-04:00"),
+            ),
+            ("utc", "2024-03-30T23:45:00+00:00", None),
+        ] {
+            sqlx::query("INSERT INTO qu_log (text, create_date, updated_date) VALUES ($1, $2, $3)")
+                .bind(text)
+                .bind(create_date)
+                .bind(updated_date)
+                .execute(&pool)
+                .await?;
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO qu_log_edit (log_id, old_text, new_text, old_tags, new_tags, edit_date)
+            VALUES (2, 'ny', 'new york', '', '', '2024-04-01T08:00:00-04:00')
+        "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        migrate(&pool).await?;
+
+        let logs: Vec<(String, String, i32)> = sqlx::query_as(
+            "SELECT text, create_date, create_offset FROM qu_log ORDER BY create_date",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(
+            logs,
+            vec![
+                (
+                    "utc".to_string(),
+                    "2024-03-30T23:45:00+00:00".to_string(),
+                    0
+                ),
+                (
+                    "new york".to_string(),
+                    "2024-03-31T00:00:00+00:00".to_string(),
+                    -5 * 3600
+                ),
+                (
+                    "berlin".to_string(),
+                    "2024-03-31T00:30:00.250+00:00".to_string(),
+                    3600
+                ),
+            ]
+        );
+
+        let updated_date: String =
+            sqlx::query_scalar("SELECT updated_date FROM qu_log WHERE text = 'new york'")
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(updated_date, "2024-04-01T12:00:00+00:00");
+        let edit_date: String = sqlx::query_scalar("SELECT edit_date FROM qu_log_edit")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(edit_date, "2024-04-01T12:00:00+00:00");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pending_migrations_does_not_touch_db() {
+        let pool = connect_to_db(&DBConfig::in_memory()).await.unwrap();
+        pending_migrations(&pool).await.unwrap();
+
+        let tables: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+}
@@ -0,0 +1,305 @@
+use chrono::{Datelike, Days, Months, TimeZone, Weekday};
+use clap::ValueEnum;
+
+use super::{clock::Clock, dates::QuLogDateSpan};
+
+/// Calendar periods logs can be filtered by. Every preset covers whole days,
+/// from the midnight starting the period to the one after it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub(super) enum QuLogDateRange {
+    Today,
+    Yesterday,
+    ThisWeek,
+    LastWeek,
+    ThisMonth,
+    LastMonth,
+    #[value(alias = "this-quarter")]
+    Quarter,
+    LastQuarter,
+    ThisYear,
+    LastYear,
+    /// The last N days, today included
+    LastNDays,
+}
+
+impl QuLogDateRange {
+    /// Period containing the time of `clock`, in its time zone. Weeks start on `week_start`,
+    /// `days` is the N of `last-n-days` and must be missing for the other presets.
+    pub(super) fn span<Tz: TimeZone>(
+        self,
+        days: Option<u32>,
+        clock: &dyn Clock<Tz>,
+        week_start: Weekday,
+    ) -> Result<QuLogDateSpan<Tz>, String> {
+        let now = clock.now();
+        let tz = now.timezone();
+        let today = now.date_naive();
+
+        let days = match (self, days) {
+            (QuLogDateRange::LastNDays, Some(0)) => {
+                return Err("last-n-days needs at least 1 day".to_string())
+            }
+            (QuLogDateRange::LastNDays, Some(days)) => days,
+            (QuLogDateRange::LastNDays, None) => {
+                return Err("last-n-days needs the number of days, like last-n-days 7".to_string())
+            }
+            (_, Some(days)) => return Err(format!("{} days can only follow last-n-days", days)),
+            (_, None) => 0,
+        };
+
+        let week = today - Days::new(today.weekday().days_since(week_start) as u64);
+        let month = today.with_day(1).unwrap();
+        let quarter = month.with_month0(month.month0() / 3 * 3).unwrap();
+        let year = quarter.with_month(1).unwrap();
+
+        let (start, end) = match self {
+            QuLogDateRange::Today => (today, today + Days::new(1)),
+            QuLogDateRange::Yesterday => (today - Days::new(1), today),
+            QuLogDateRange::ThisWeek => (week, week + Days::new(7)),
+            QuLogDateRange::LastWeek => (week - Days::new(7), week),
+            QuLogDateRange::ThisMonth => (month, month + Months::new(1)),
+            QuLogDateRange::LastMonth => (month - Months::new(1), month),
+            QuLogDateRange::Quarter => (quarter, quarter + Months::new(3)),
+            QuLogDateRange::LastQuarter => (quarter - Months::new(3), quarter),
+            QuLogDateRange::ThisYear => (year, year + Months::new(12)),
+            QuLogDateRange::LastYear => (year - Months::new(12), year),
+            QuLogDateRange::LastNDays => {
+                let start = today
+                    .checked_sub_days(Days::new(days as u64 - 1))
+                    .ok_or_else(|| format!("last-n-days {} goes too far back", days))?;
+                (start, today + Days::new(1))
+            }
+        };
+
+        Ok(QuLogDateSpan::between(&tz, start, end))
+    }
+}
+
+/// Parses `monday`, `mon`, `sunday`... for `--week-start`.
+pub(super) fn parse_week_start(value: &str) -> Result<Weekday, String> {
+    value.parse().map_err(|_| {
+        format!(
+            "invalid week start {:?}, expected a weekday like monday",
+            value
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+    use chrono_tz::{America::Sao_Paulo, Europe::Berlin};
+
+    use super::super::clock::FixedClock;
+    use super::*;
+
+    /// Wednesday 2025-05-14, the fake clock every preset is resolved against
+    fn clock() -> FixedClock<Utc> {
+        FixedClock(Utc.with_ymd_and_hms(2025, 5, 14, 15, 30, 0).unwrap())
+    }
+
+    fn span(range: QuLogDateRange) -> (String, String) {
+        span_with(range, None, Weekday::Mon)
+    }
+
+    fn span_with(
+        range: QuLogDateRange,
+        days: Option<u32>,
+        week_start: Weekday,
+    ) -> (String, String) {
+        let span = range.span(days, &clock(), week_start).unwrap();
+        (
+            span.start.format("%Y-%m-%d %H:%M").to_string(),
+            span.end.format("%Y-%m-%d %H:%M").to_string(),
+        )
+    }
+
+    fn expected(start: &str, end: &str) -> (String, String) {
+        (format!("{} 00:00", start), format!("{} 00:00", end))
+    }
+
+    #[test]
+    fn test_day_presets() {
+        assert_eq!(
+            span(QuLogDateRange::Today),
+            expected("2025-05-14", "2025-05-15")
+        );
+        assert_eq!(
+            span(QuLogDateRange::Yesterday),
+            expected("2025-05-13", "2025-05-14")
+        );
+        assert_eq!(
+            span_with(QuLogDateRange::LastNDays, Some(1), Weekday::Mon),
+            expected("2025-05-14", "2025-05-15")
+        );
+        assert_eq!(
+            span_with(QuLogDateRange::LastNDays, Some(30), Weekday::Mon),
+            expected("2025-04-15", "2025-05-15")
+        );
+    }
+
+    #[test]
+    fn test_week_presets() {
+        assert_eq!(
+            span(QuLogDateRange::ThisWeek),
+            expected("2025-05-12", "2025-05-19")
+        );
+        assert_eq!(
+            span(QuLogDateRange::LastWeek),
+            expected("2025-05-05", "2025-05-12")
+        );
+        assert_eq!(
+            span_with(QuLogDateRange::ThisWeek, None, Weekday::Sun),
+            expected("2025-05-11", "2025-05-18")
+        );
+        assert_eq!(
+            span_with(QuLogDateRange::LastWeek, None, Weekday::Sat),
+            expected("2025-05-03", "2025-05-10")
+        );
+        // A week starting today begins today
+        assert_eq!(
+            span_with(QuLogDateRange::ThisWeek, None, Weekday::Wed),
+            expected("2025-05-14", "2025-05-21")
+        );
+    }
+
+    #[test]
+    fn test_calendar_presets() {
+        assert_eq!(
+            span(QuLogDateRange::ThisMonth),
+            expected("2025-05-01", "2025-06-01")
+        );
+        assert_eq!(
+            span(QuLogDateRange::LastMonth),
+            expected("2025-04-01", "2025-05-01")
+        );
+        assert_eq!(
+            span(QuLogDateRange::Quarter),
+            expected("2025-04-01", "2025-07-01")
+        );
+        assert_eq!(
+            span(QuLogDateRange::LastQuarter),
+            expected("2025-01-01", "2025-04-01")
+        );
+        assert_eq!(
+            span(QuLogDateRange::ThisYear),
+            expected("2025-01-01", "2026-01-01")
+        );
+        assert_eq!(
+            span(QuLogDateRange::LastYear),
+            expected("2024-01-01", "2025-01-01")
+        );
+    }
+
+    #[test]
+    fn test_presets_across_year_boundary() {
+        let clock = FixedClock(Utc.with_ymd_and_hms(2025, 1, 2, 9, 0, 0).unwrap());
+        let span = |range: QuLogDateRange| {
+            let span = range.span(None, &clock, Weekday::Mon).unwrap();
+            (span.start.date_naive(), span.end.date_naive())
+        };
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        assert_eq!(
+            span(QuLogDateRange::LastMonth),
+            (date(2024, 12, 1), date(2025, 1, 1))
+        );
+        assert_eq!(
+            span(QuLogDateRange::LastQuarter),
+            (date(2024, 10, 1), date(2025, 1, 1))
+        );
+        assert_eq!(
+            span(QuLogDateRange::ThisWeek),
+            (date(2024, 12, 30), date(2025, 1, 6))
+        );
+    }
+
+    #[test]
+    fn test_invalid_days() {
+        assert!(QuLogDateRange::LastNDays
+            .span(None, &clock(), Weekday::Mon)
+            .is_err());
+        assert!(QuLogDateRange::LastNDays
+            .span(Some(0), &clock(), Weekday::Mon)
+            .is_err());
+        assert!(QuLogDateRange::LastNDays
+            .span(Some(u32::MAX), &clock(), Weekday::Mon)
+            .is_err());
+        assert!(QuLogDateRange::Today
+            .span(Some(3), &clock(), Weekday::Mon)
+            .is_err());
+    }
+
+    #[test]
+    fn test_boundaries_across_dst() {
+        // Berlin moved its clocks forward on 2025-03-30, that week is an hour short
+        let clock = FixedClock(Berlin.with_ymd_and_hms(2025, 4, 2, 12, 0, 0).unwrap());
+        let span = QuLogDateRange::LastWeek
+            .span(None, &clock, Weekday::Mon)
+            .unwrap();
+        assert_eq!(
+            span.start,
+            Berlin.with_ymd_and_hms(2025, 3, 24, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            span.end,
+            Berlin.with_ymd_and_hms(2025, 3, 31, 0, 0, 0).unwrap()
+        );
+        assert_eq!(span.end - span.start, Duration::hours(7 * 24 - 1));
+
+        // and back on 2025-10-26, that day has 25 hours
+        let clock = FixedClock(Berlin.with_ymd_and_hms(2025, 10, 27, 8, 0, 0).unwrap());
+        let span = QuLogDateRange::Yesterday
+            .span(None, &clock, Weekday::Mon)
+            .unwrap();
+        assert_eq!(span.end - span.start, Duration::hours(25));
+
+        // Sao Paulo skipped midnight on 2018-11-04, that day started at 01:00
+        let clock = FixedClock(Sao_Paulo.with_ymd_and_hms(2018, 11, 4, 12, 0, 0).unwrap());
+        let span = QuLogDateRange::Today
+            .span(None, &clock, Weekday::Mon)
+            .unwrap();
+        let start: NaiveDateTime = "2018-11-04T01:00:00".parse().unwrap();
+        assert_eq!(span.start.naive_local(), start);
+        assert_eq!(
+            span.end,
+            Sao_Paulo.with_ymd_and_hms(2018, 11, 5, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_every_preset_in_every_time_zone() {
+        fn check<Tz: TimeZone>(clock: FixedClock<Tz>) {
+            let now = clock.now();
+            for range in QuLogDateRange::value_variants() {
+                let days = (*range == QuLogDateRange::LastNDays).then_some(10);
+                let span = range.span(days, &clock, Weekday::Mon).unwrap();
+
+                assert!(span.start < span.end, "{:?}", range);
+                assert_eq!(span.start.time(), chrono::NaiveTime::MIN, "{:?}", range);
+                assert_eq!(span.end.time(), chrono::NaiveTime::MIN, "{:?}", range);
+
+                let current = !matches!(
+                    range,
+                    QuLogDateRange::Yesterday
+                        | QuLogDateRange::LastWeek
+                        | QuLogDateRange::LastMonth
+                        | QuLogDateRange::LastQuarter
+                        | QuLogDateRange::LastYear
+                );
+                assert_eq!(span.start <= now && now < span.end, current, "{:?}", range);
+            }
+        }
+
+        let instant = Utc.with_ymd_and_hms(2025, 12, 31, 23, 30, 0).unwrap();
+        check(FixedClock(instant));
+        check(FixedClock(instant.with_timezone(&Berlin)));
+        check(FixedClock(instant.with_timezone(&chrono_tz::Asia::Kolkata)));
+        check(FixedClock(
+            instant.with_timezone(&chrono_tz::Pacific::Auckland),
+        ));
+        check(FixedClock(
+            instant.with_timezone(&chrono_tz::America::Los_Angeles),
+        ));
+    }
+}
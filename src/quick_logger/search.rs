@@ -0,0 +1,236 @@
+use sqlx::{sqlite::SqlitePool, FromRow};
+
+use super::{error::QuLogError, QuLog, QuLogDBO, QU_LOG_COLUMNS};
+
+/// Markers put around matched terms in search snippets.
+pub(super) struct Highlight {
+    open: &'static str,
+    close: &'static str,
+}
+
+pub(super) const TERMINAL_HIGHLIGHT: Highlight = Highlight {
+    open: "\x1b[1;33m",
+    close: "\x1b[0m",
+};
+
+pub(super) const NO_HIGHLIGHT: Highlight = Highlight {
+    open: "",
+    close: "",
+};
+
+pub(super) struct QuLogSearchHit {
+    pub(super) log: QuLog,
+    pub(super) snippet: String,
+}
+
+#[derive(Debug, FromRow)]
+struct QuLogSearchHitDBO {
+    #[sqlx(flatten)]
+    log: QuLogDBO,
+    snippet: String,
+}
+
+/// Searches log texts with an FTS5 `query` (phrases, `prefix*`, `AND`/`OR`/`NOT`),
+/// best matches first by bm25. A malformed `query` is a `Parse` error.
+pub(super) async fn search_logs(
+    pool: &SqlitePool,
+    query: &str,
+    limit: i64,
+    highlight: Highlight,
+) -> Result<Vec<QuLogSearchHit>, QuLogError> {
+    // Matched alone first, so only its errors can be blamed on the query and not
+    // the ones of a schema qulog doesn't expect, reported with the same code
+    let checked = sqlx::query("SELECT 1 FROM qu_log_fts WHERE qu_log_fts MATCH $1 LIMIT 1")
+        .bind(query)
+        .fetch_optional(pool)
+        .await;
+    if let Err(err) = checked {
+        if is_query_syntax_error(&err) {
+            return Err(QuLogError::Parse(format!(
+                "Invalid search query {:?}: {}",
+                query, err
+            )));
+        }
+        return Err(err.into());
+    }
+
+    let hits = sqlx::query_as::<_, QuLogSearchHitDBO>(&format!(
+        r#"
+        SELECT {QU_LOG_COLUMNS}, snippet(qu_log_fts, 0, $2, $3, '…', 16) AS snippet
+        FROM qu_log_fts JOIN qu_log ON qu_log.id = qu_log_fts.rowid
+        WHERE qu_log_fts MATCH $1
+        ORDER BY bm25(qu_log_fts)
+        LIMIT $4
+    "#
+    ))
+    .bind(query)
+    .bind(highlight.open)
+    .bind(highlight.close)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|hit| QuLogSearchHit {
+        log: hit.log.into(),
+        snippet: hit.snippet,
+    })
+    .collect();
+
+    Ok(hits)
+}
+
+/// FTS5 reports malformed queries ("fts5: syntax error near ...", "unterminated string",
+/// "no such column: bar" for `foo-bar`) as a plain `SQLITE_ERROR` while stepping the statement,
+/// like schema errors ("no such table: ...") which are told apart by their message.
+fn is_query_syntax_error(err: &sqlx::Error) -> bool {
+    match err {
+        sqlx::Error::Database(err) if err.code().as_deref() == Some("1") => {
+            let message = err.message();
+            message.starts_with("fts5: syntax error")
+                || message.starts_with("unterminated string")
+                || message.contains("no such column")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::Local;
+
+    use super::super::{connect_to_db, create_log, migrations, DBConfig, QuLogLevel, QuLogTags};
+    use super::*;
+
+    async fn pool_with(texts: &[&str]) -> SqlitePool {
+        let pool = connect_to_db(&DBConfig::in_memory()).await.unwrap();
+        migrations::migrate(&pool).await.unwrap();
+
+        for text in texts {
+            let log = QuLog {
+                id: None,
+                text: text.to_string(),
+                tags: QuLogTags::empty(),
+                level: QuLogLevel::default(),
+                source: None,
+                fields: BTreeMap::new(),
+                attachments: Vec::new(),
+                create_date: Local::now().fixed_offset(),
+                updated_date: None,
+            };
+            create_log(&log, &pool).await.unwrap();
+        }
+        pool
+    }
+
+    async fn search(pool: &SqlitePool, query: &str) -> Vec<String> {
+        search_logs(pool, query, 20, NO_HIGHLIGHT)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.log.text)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_search_words_phrases_and_prefixes() {
+        let pool = pool_with(&[
+            "learning rust ownership",
+            "ownership of the rust book",
+            "rustacean meetup",
+            "went running",
+        ])
+        .await;
+
+        assert_eq!(search(&pool, "running").await, vec!["went running"]);
+        assert_eq!(
+            search(&pool, "\"rust ownership\"").await,
+            vec!["learning rust ownership"]
+        );
+        assert_eq!(search(&pool, "rust*").await.len(), 3);
+        assert_eq!(
+            search(&pool, "rust NOT book").await,
+            vec!["learning rust ownership"]
+        );
+        assert!(search(&pool, "python").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_by_bm25() {
+        let pool = pool_with(&[
+            "a long day with meetings and one mention of rust somewhere",
+            "rust rust rust",
+        ])
+        .await;
+
+        assert_eq!(
+            search(&pool, "rust").await.first().unwrap(),
+            "rust rust rust"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_highlights_snippet() {
+        let pool = pool_with(&["learning rust ownership"]).await;
+
+        let hits = search_logs(
+            &pool,
+            "rust",
+            20,
+            Highlight {
+                open: "[",
+                close: "]",
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(hits[0].snippet, "learning [rust] ownership");
+    }
+
+    #[tokio::test]
+    async fn test_search_follows_updates_and_deletes() {
+        let pool = pool_with(&["first draft", "to be removed"]).await;
+
+        sqlx::query("UPDATE qu_log SET text = 'final version' WHERE text = 'first draft'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM qu_log WHERE text = 'to be removed'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(search(&pool, "draft").await.is_empty());
+        assert!(search(&pool, "removed").await.is_empty());
+        assert_eq!(search(&pool, "final").await, vec!["final version"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_reports_syntax_errors() {
+        let pool = pool_with(&["hello"]).await;
+
+        for query in ["\"unbalanced", "foo-bar", "AND"] {
+            let result = search_logs(&pool, query, 20, NO_HIGHLIGHT).await;
+            assert!(matches!(result, Err(QuLogError::Parse(_))), "{:?}", query);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_schema_errors_are_not_syntax_errors() {
+        for schema_change in [
+            "DROP TABLE qu_log_fts",
+            "ALTER TABLE qu_log RENAME COLUMN fields TO old_fields",
+        ] {
+            let pool = pool_with(&["hello"]).await;
+            sqlx::query(schema_change).execute(&pool).await.unwrap();
+
+            let result = search_logs(&pool, "hello", 20, NO_HIGHLIGHT).await;
+            assert!(
+                matches!(result, Err(QuLogError::Db(_))),
+                "{}",
+                schema_change
+            );
+        }
+    }
+}
@@ -0,0 +1,445 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Datelike, Days, Months, NaiveDate, Timelike, Weekday};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use super::{dates::QuLogDateDisplay, QuLog};
+
+/// Length of the periods logs are counted by.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub(super) enum QuLogStatsPeriod {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl QuLogStatsPeriod {
+    /// First day of the period `day` is in.
+    fn start(self, day: NaiveDate, week_start: Weekday) -> NaiveDate {
+        match self {
+            QuLogStatsPeriod::Day => day,
+            QuLogStatsPeriod::Week => day - Days::new(day.weekday().days_since(week_start) as u64),
+            QuLogStatsPeriod::Month => day.with_day(1).unwrap(),
+        }
+    }
+
+    fn next(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            QuLogStatsPeriod::Day => start + Days::new(1),
+            QuLogStatsPeriod::Week => start + Days::new(7),
+            QuLogStatsPeriod::Month => start + Months::new(1),
+        }
+    }
+}
+
+/// How much was logged, as printed by `qulog stats` or written by `--json`.
+/// Days and hours are read in the display time zone.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub(super) struct QuLogStats {
+    pub(super) total: usize,
+    pub(super) first_day: Option<NaiveDate>,
+    pub(super) last_day: Option<NaiveDate>,
+    /// Every period from the first log to the last, the ones without logs included
+    pub(super) periods: Vec<QuLogPeriodCount>,
+    /// Most used tags first, ties by name
+    pub(super) top_tags: Vec<QuLogTagCount>,
+    pub(super) longest_streak: Option<QuLogStreak>,
+    /// Hour of the day most logs were written at, the earliest one on ties
+    pub(super) busiest_hour: Option<u32>,
+    /// Number of logs written at each hour of the day, midnight first
+    pub(super) hours: Vec<usize>,
+    /// Number of logs of each day having some
+    #[serde(skip)]
+    pub(super) days: BTreeMap<NaiveDate, usize>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(super) struct QuLogPeriodCount {
+    pub(super) start: NaiveDate,
+    pub(super) count: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(super) struct QuLogTagCount {
+    pub(super) tag: String,
+    pub(super) count: usize,
+}
+
+/// Days in a row with at least one log.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(super) struct QuLogStreak {
+    pub(super) days: usize,
+    pub(super) start: NaiveDate,
+    pub(super) end: NaiveDate,
+}
+
+impl QuLogStats {
+    /// Counts `logs` by `period`, keeping the `top` most used tags.
+    pub(super) fn new(
+        logs: &[QuLog],
+        display: &QuLogDateDisplay,
+        period: QuLogStatsPeriod,
+        week_start: Weekday,
+        top: usize,
+    ) -> Self {
+        let mut stats = QuLogStats {
+            total: logs.len(),
+            hours: vec![0; 24],
+            ..Default::default()
+        };
+
+        let mut tags: HashMap<&str, usize> = HashMap::new();
+        for log in logs {
+            let date = display.convert(log.create_date);
+            *stats.days.entry(date.date_naive()).or_default() += 1;
+            stats.hours[date.hour() as usize] += 1;
+            for tag in &log.tags.0 {
+                *tags.entry(tag).or_default() += 1;
+            }
+        }
+
+        let mut tags: Vec<QuLogTagCount> = tags
+            .into_iter()
+            .map(|(tag, count)| QuLogTagCount {
+                tag: tag.to_string(),
+                count,
+            })
+            .collect();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+        tags.truncate(top);
+        stats.top_tags = tags;
+
+        stats.first_day = stats.days.keys().next().copied();
+        stats.last_day = stats.days.keys().next_back().copied();
+
+        if let (Some(first), Some(last)) = (stats.first_day, stats.last_day) {
+            let mut start = period.start(first, week_start);
+            while start <= last {
+                let next = period.next(start);
+                stats.periods.push(QuLogPeriodCount {
+                    start,
+                    count: stats.days.range(start..next).map(|(_, count)| count).sum(),
+                });
+                start = next;
+            }
+        }
+
+        stats.longest_streak = longest_streak(stats.days.keys().copied());
+
+        let busiest = stats.hours.iter().max().copied().unwrap_or_default();
+        stats.busiest_hour = stats
+            .hours
+            .iter()
+            .position(|count| *count == busiest && busiest > 0)
+            .map(|hour| hour as u32);
+
+        stats
+    }
+}
+
+/// Longest run of consecutive `days`, given in order. The earliest one wins ties.
+fn longest_streak(days: impl Iterator<Item = NaiveDate>) -> Option<QuLogStreak> {
+    let mut longest: Option<QuLogStreak> = None;
+    let mut current: Option<QuLogStreak> = None;
+
+    for day in days {
+        current = match current {
+            Some(streak) if streak.end.succ_opt() == Some(day) => Some(QuLogStreak {
+                days: streak.days + 1,
+                end: day,
+                ..streak
+            }),
+            _ => Some(QuLogStreak {
+                days: 1,
+                start: day,
+                end: day,
+            }),
+        };
+
+        let streak = current.as_ref().unwrap();
+        if longest
+            .as_ref()
+            .is_none_or(|longest| streak.days > longest.days)
+        {
+            longest = Some(streak.clone());
+        }
+    }
+    longest
+}
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// One bar per count, scaled to the highest one.
+pub(super) fn sparkline(counts: impl IntoIterator<Item = usize> + Clone) -> String {
+    let max = counts.clone().into_iter().max().unwrap_or_default();
+    counts
+        .into_iter()
+        .map(|count| match count {
+            0 => ' ',
+            count => SPARKS[(count * (SPARKS.len() - 1)).div_ceil(max.max(1))],
+        })
+        .collect()
+}
+
+/// Shades of a day in the calendar, from no log to the busiest day.
+const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+/// A calendar of the `weeks` weeks ending with `last_day`, one row per weekday starting
+/// with `week_start` and one column per week, days are shaded by their number of logs.
+pub(super) fn heatmap(
+    days: &BTreeMap<NaiveDate, usize>,
+    last_day: NaiveDate,
+    week_start: Weekday,
+    weeks: u64,
+) -> String {
+    let first_week =
+        QuLogStatsPeriod::Week.start(last_day, week_start) - Days::new(7 * (weeks - 1));
+    let max = days
+        .range(first_week..)
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or_default();
+
+    let mut rows = vec![];
+    for weekday in 0..7 {
+        let day_of_week = first_week + Days::new(weekday);
+        let mut row = format!("{} ", day_of_week.format("%a"));
+        for week in 0..weeks {
+            let day = day_of_week + Days::new(7 * week);
+            if day > last_day {
+                break;
+            }
+            row.push(match days.get(&day).copied().unwrap_or_default() {
+                0 => SHADES[0],
+                count => SHADES[(count * (SHADES.len() - 1)).div_ceil(max.max(1))],
+            });
+        }
+        rows.push(row.trim_end().to_string());
+    }
+    rows.join("\n")
+}
+
+/// The stats as printed on the terminal.
+pub(super) fn render(stats: &QuLogStats, period: QuLogStatsPeriod, week_start: Weekday) -> String {
+    let (Some(first_day), Some(last_day)) = (stats.first_day, stats.last_day) else {
+        return "No record is found".to_string();
+    };
+
+    let mut lines = vec![format!(
+        "{} logs from {} to {}",
+        stats.total, first_day, last_day
+    )];
+
+    let name = match period {
+        QuLogStatsPeriod::Day => "day",
+        QuLogStatsPeriod::Week => "week",
+        QuLogStatsPeriod::Month => "month",
+    };
+    lines.push(String::new());
+    lines.push(format!("Logs per {}:", name));
+    // The latest periods with logs are the ones worth reading, the rest stays in the sparkline
+    let latest: Vec<&QuLogPeriodCount> = stats
+        .periods
+        .iter()
+        .rev()
+        .filter(|count| count.count > 0)
+        .take(12)
+        .collect();
+    for count in latest.into_iter().rev() {
+        lines.push(format!("  {}  {}", count.start, count.count));
+    }
+    lines.push(format!(
+        "  {}",
+        sparkline(stats.periods.iter().map(|count| count.count))
+    ));
+
+    if !stats.top_tags.is_empty() {
+        lines.push(String::new());
+        lines.push("Top tags:".to_string());
+        let width = stats
+            .top_tags
+            .iter()
+            .map(|tag| tag.tag.chars().count())
+            .max()
+            .unwrap_or(0);
+        for tag in &stats.top_tags {
+            lines.push(format!("  {:width$}  {}", tag.tag, tag.count));
+        }
+    }
+
+    if let Some(streak) = &stats.longest_streak {
+        lines.push(String::new());
+        lines.push(format!(
+            "Longest streak: {} day(s), {} to {}",
+            streak.days, streak.start, streak.end
+        ));
+    }
+
+    if let Some(hour) = stats.busiest_hour {
+        lines.push(format!(
+            "Busiest hour: {:02}:00-{:02}:00 ({} logs)",
+            hour,
+            (hour + 1) % 24,
+            stats.hours[hour as usize]
+        ));
+        lines.push(format!("  {}", sparkline(stats.hours.iter().copied())));
+        lines.push("  0     6     12    18   23".to_string());
+    }
+
+    lines.push(String::new());
+    lines.push(heatmap(&stats.days, last_day, week_start, 26));
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::super::dates::QuLogTimeZone;
+    use super::*;
+
+    fn display() -> QuLogDateDisplay {
+        QuLogDateDisplay {
+            format: "%Y-%m-%d".to_string(),
+            tz: QuLogTimeZone::Utc,
+        }
+    }
+
+    fn log(day: u32, hour: u32, tags: &[&str]) -> QuLog {
+        let mut log = QuLog::new("log", tags.iter().copied());
+        log.create_date = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2025, 3, day, hour, 0, 0)
+            .unwrap();
+        log
+    }
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, month, day).unwrap()
+    }
+
+    fn logs() -> Vec<QuLog> {
+        vec![
+            // Saturday the 1st to monday the 3rd, then the 5th and the 6th
+            log(1, 9, &["work"]),
+            log(2, 9, &["rust", "work"]),
+            log(3, 21, &["rust"]),
+            log(3, 9, &["work"]),
+            log(5, 9, &[]),
+            log(6, 10, &["sport"]),
+            // A week later
+            log(13, 10, &["work"]),
+        ]
+    }
+
+    #[test]
+    fn test_stats_counts() {
+        let stats = QuLogStats::new(&logs(), &display(), QuLogStatsPeriod::Day, Weekday::Mon, 2);
+
+        assert_eq!(stats.total, 7);
+        assert_eq!(stats.first_day, Some(date(3, 1)));
+        assert_eq!(stats.last_day, Some(date(3, 13)));
+        assert_eq!(stats.periods.len(), 13);
+        assert_eq!(stats.periods[2].count, 2);
+        assert_eq!(stats.periods[3].count, 0);
+        assert_eq!(
+            stats.top_tags,
+            vec![
+                QuLogTagCount {
+                    tag: "work".to_string(),
+                    count: 4
+                },
+                QuLogTagCount {
+                    tag: "rust".to_string(),
+                    count: 2
+                },
+            ]
+        );
+        assert_eq!(
+            stats.longest_streak,
+            Some(QuLogStreak {
+                days: 3,
+                start: date(3, 1),
+                end: date(3, 3)
+            })
+        );
+        assert_eq!(stats.busiest_hour, Some(9));
+        assert_eq!(stats.hours[9], 4);
+        assert_eq!(stats.hours[21], 1);
+    }
+
+    #[test]
+    fn test_stats_periods() {
+        let counts = |period| {
+            QuLogStats::new(&logs(), &display(), period, Weekday::Mon, 5)
+                .periods
+                .into_iter()
+                .map(|count| (count.start, count.count))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            counts(QuLogStatsPeriod::Week),
+            vec![(date(2, 24), 2), (date(3, 3), 4), (date(3, 10), 1)]
+        );
+        assert_eq!(counts(QuLogStatsPeriod::Month), vec![(date(3, 1), 7)]);
+
+        let sunday_weeks =
+            QuLogStats::new(&logs(), &display(), QuLogStatsPeriod::Week, Weekday::Sun, 5);
+        assert_eq!(sunday_weeks.periods[0].start, date(2, 23));
+        assert_eq!(sunday_weeks.periods[1].count, 5);
+    }
+
+    #[test]
+    fn test_stats_follow_display_time_zone() {
+        // 23:30 UTC is already the next day in Tokyo
+        let mut late = log(1, 23, &[]);
+        late.create_date += chrono::Duration::minutes(30);
+
+        let tokyo = QuLogDateDisplay {
+            tz: QuLogTimeZone::Named(chrono_tz::Asia::Tokyo),
+            ..display()
+        };
+        let stats = QuLogStats::new(&[late], &tokyo, QuLogStatsPeriod::Day, Weekday::Mon, 5);
+        assert_eq!(stats.first_day, Some(date(3, 2)));
+        assert_eq!(stats.busiest_hour, Some(8));
+    }
+
+    #[test]
+    fn test_stats_empty() {
+        let stats = QuLogStats::new(&[], &display(), QuLogStatsPeriod::Day, Weekday::Mon, 5);
+        assert_eq!(stats.total, 0);
+        assert!(stats.periods.is_empty());
+        assert_eq!(stats.longest_streak, None);
+        assert_eq!(stats.busiest_hour, None);
+        assert_eq!(
+            render(&stats, QuLogStatsPeriod::Day, Weekday::Mon),
+            "No record is found"
+        );
+    }
+
+    #[test]
+    fn test_sparkline_and_heatmap() {
+        assert_eq!(sparkline([0, 1, 2, 4, 8]), " ▂▃▅█");
+        assert_eq!(sparkline(Vec::<usize>::new()), "");
+
+        let stats = QuLogStats::new(&logs(), &display(), QuLogStatsPeriod::Day, Weekday::Mon, 5);
+        let calendar = heatmap(&stats.days, date(3, 13), Weekday::Mon, 3);
+        assert_eq!(
+            calendar,
+            [
+                "Mon ·█·",
+                "Tue ···",
+                "Wed ·▒·",
+                "Thu ·▒▒",
+                "Fri ··",
+                "Sat ▒·",
+                "Sun ▒·"
+            ]
+            .join("\n")
+        );
+    }
+}
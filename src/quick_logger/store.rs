@@ -0,0 +1,402 @@
+mod jsonl;
+mod memory;
+
+use std::{future::Future, path::PathBuf, pin::Pin};
+
+use chrono::{DateTime, Utc};
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
+};
+use sqlx::sqlite::SqlitePool;
+
+use super::{
+    attachment::{self, QuLogAttachment, QuLogAttachmentFile},
+    config, connect_to_db, count_logs, create_log, delete_logs,
+    error::QuLogError,
+    fetch_log, fetch_logs, migrations, stream_logs, update_log, DBConfig, QuLog, QuLogBackend,
+    QuLogFilter, QuLogUpdate,
+};
+
+pub use jsonl::JsonlLogStore;
+pub use memory::MemoryLogStore;
+
+/// Future returned by [`LogStore`] methods, boxed so stores can be picked at runtime
+/// as a `Box<dyn LogStore>` and sent across tasks.
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, QuLogError>> + Send + 'a>>;
+
+/// Logs handed out one at a time by [`LogStore::fetch`].
+pub type LogStream<'a> = BoxStream<'a, Result<QuLog, QuLogError>>;
+
+/// Where logs are kept: [`QuLogStore`] (SQLite), [`JsonlLogStore`] (an append-only file)
+/// or [`MemoryLogStore`].
+/// Saving and reading logs is required, stores that can't delete or edit logs
+/// keep the provided methods, which fail with [`QuLogError::Validation`].
+pub trait LogStore: Send + Sync {
+    /// Saves `log` with its tags and returns its id, `log.id` is ignored.
+    fn insert<'a>(&'a self, log: &'a QuLog) -> StoreFuture<'a, i64>;
+
+    /// Logs matching `filter`, ordered by the date they were written.
+    fn query<'a>(&'a self, filter: &'a QuLogFilter) -> StoreFuture<'a, Vec<QuLog>>;
+
+    /// `query` as a stream, stores that can read logs lazily do so
+    /// instead of collecting them first.
+    fn fetch<'a>(&'a self, filter: &'a QuLogFilter) -> LogStream<'a> {
+        stream::once(self.query(filter))
+            .map_ok(|logs| stream::iter(logs.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+
+    /// Number of logs matching `filter`.
+    fn count<'a>(&'a self, filter: &'a QuLogFilter) -> StoreFuture<'a, i64> {
+        Box::pin(async move { Ok(self.query(filter).await?.len() as i64) })
+    }
+
+    /// Deletes the logs matching `filter` and returns how many were deleted.
+    fn delete<'a>(&'a self, _filter: &'a QuLogFilter) -> StoreFuture<'a, u64> {
+        Box::pin(async { Err(unsupported("deleted")) })
+    }
+
+    /// Applies `update` to the log with `id` as if it happened at `now`.
+    /// Returns the updated log, or `None` when there is no log with `id`.
+    fn update_at<'a>(
+        &'a self,
+        _id: i64,
+        _update: &'a QuLogUpdate,
+        _now: DateTime<Utc>,
+    ) -> StoreFuture<'a, Option<QuLog>> {
+        Box::pin(async { Err(unsupported("edited")) })
+    }
+
+    /// The SQLite database behind the store, needed for search, edit history and migrations.
+    fn as_sqlite(&self) -> Option<&QuLogStore> {
+        None
+    }
+}
+
+fn unsupported(action: &str) -> QuLogError {
+    QuLogError::Validation(format!(
+        "Logs of this store can't be {}, use a SQLite database",
+        action
+    ))
+}
+
+/// A qulog database, the same one the `qulog` command writes to.
+///
+/// ```
+/// use rand_sht::quick_logger::{QuLog, QuLogFilter, QuLogStore, QuLogTagMatch};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), rand_sht::quick_logger::QuLogError> {
+/// let store = QuLogStore::in_memory().await?;
+/// store.insert(&QuLog::new("deployed v2", ["work", "release"])).await?;
+/// store.insert(&QuLog::new("went for a run", ["sport"])).await?;
+///
+/// let filter = QuLogFilter::builder()
+///     .tags(["release", "incident"])
+///     .tag_match(QuLogTagMatch::Any)
+///     .build();
+/// let logs = store.query(&filter).await?;
+/// assert_eq!(logs.len(), 1);
+/// assert_eq!(logs[0].text, "deployed v2");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct QuLogStore {
+    pub(super) pool: SqlitePool,
+}
+
+impl QuLogStore {
+    /// Opens the database file at `path`, creating it when it doesn't exist,
+    /// and applies pending migrations.
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self, QuLogError> {
+        let path = path.into();
+        let store = QuLogStore::connect(&DBConfig {
+            in_memory: path.as_os_str() == ":memory:",
+            path,
+            path_source: config::DBPathSource::Library,
+            backend: QuLogBackend::Sqlite,
+        })
+        .await?;
+        store.migrate().await?;
+        Ok(store)
+    }
+
+    /// A database living in memory, gone once the store and its clones are dropped.
+    pub async fn in_memory() -> Result<Self, QuLogError> {
+        QuLogStore::open(":memory:").await
+    }
+
+    /// Connects without migrating, so `db` commands can look at the schema as it is.
+    pub(super) async fn connect(config: &DBConfig) -> Result<Self, QuLogError> {
+        Ok(QuLogStore {
+            pool: connect_to_db(config).await?,
+        })
+    }
+
+    pub(super) async fn migrate(&self) -> Result<(), QuLogError> {
+        migrations::migrate(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Saves `log` with its tags and returns its id, `log.id` is ignored.
+    pub async fn insert(&self, log: &QuLog) -> Result<i64, QuLogError> {
+        Ok(create_log(log, &self.pool).await?)
+    }
+
+    /// The log with `id`, if there is one.
+    pub async fn get(&self, id: i64) -> Result<Option<QuLog>, QuLogError> {
+        let mut conn = self.pool.acquire().await?;
+        Ok(fetch_log(&mut conn, id).await?)
+    }
+
+    /// Logs matching `filter`, `QuLogFilter::default()` matches every log.
+    pub async fn query(&self, filter: &QuLogFilter) -> Result<Vec<QuLog>, QuLogError> {
+        Ok(fetch_logs(&self.pool, filter).await?)
+    }
+
+    /// Logs matching `filter`, read from the database as the stream is polled.
+    ///
+    /// ```
+    /// use futures::TryStreamExt;
+    /// # use rand_sht::quick_logger::{QuLog, QuLogFilter, QuLogSort, QuLogStore};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), rand_sht::quick_logger::QuLogError> {
+    /// let store = QuLogStore::in_memory().await?;
+    /// for text in ["one", "two", "three"] {
+    ///     store.insert(&QuLog::new(text, Vec::<String>::new())).await?;
+    /// }
+    ///
+    /// let filter = QuLogFilter::builder().sort(QuLogSort::Desc).limit(2).build();
+    /// let mut logs = store.fetch(&filter);
+    /// while let Some(log) = logs.try_next().await? {
+    ///     println!("{}", log.text);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fetch(&self, filter: &QuLogFilter) -> LogStream<'static> {
+        stream_logs(&self.pool, filter).err_into().boxed()
+    }
+
+    /// Number of logs matching `filter`.
+    pub async fn count(&self, filter: &QuLogFilter) -> Result<i64, QuLogError> {
+        Ok(count_logs(&self.pool, filter).await?)
+    }
+
+    /// Deletes the logs matching `filter` and returns how many were deleted.
+    ///
+    /// ```
+    /// # use rand_sht::quick_logger::{QuLog, QuLogFilter, QuLogStore};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), rand_sht::quick_logger::QuLogError> {
+    /// let store = QuLogStore::in_memory().await?;
+    /// store.insert(&QuLog::new("draft", ["draft"])).await?;
+    /// store.insert(&QuLog::new("keep me", Vec::<String>::new())).await?;
+    ///
+    /// let deleted = store
+    ///     .delete(&QuLogFilter::builder().tags(["draft"]).build())
+    ///     .await?;
+    /// assert_eq!(deleted, 1);
+    /// assert_eq!(store.count(&QuLogFilter::default()).await?, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete(&self, filter: &QuLogFilter) -> Result<u64, QuLogError> {
+        Ok(delete_logs(&self.pool, filter).await?)
+    }
+
+    /// Applies `update` to the log with `id`, the previous version is kept in its history.
+    /// Returns the updated log, or `None` when there is no log with `id`.
+    ///
+    /// ```
+    /// # use rand_sht::quick_logger::{QuLog, QuLogStore, QuLogUpdate};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), rand_sht::quick_logger::QuLogError> {
+    /// let store = QuLogStore::in_memory().await?;
+    /// let id = store.insert(&QuLog::new("helo", ["typo"])).await?;
+    ///
+    /// let update = QuLogUpdate {
+    ///     text: Some("hello".to_string()),
+    ///     remove_tags: vec!["typo".to_string()].into(),
+    ///     ..Default::default()
+    /// };
+    /// let log = store.update(id, &update).await?.unwrap();
+    /// assert_eq!(log.text, "hello");
+    /// assert!(log.tags.0.is_empty());
+    /// assert!(log.updated_date.is_some());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn update(&self, id: i64, update: &QuLogUpdate) -> Result<Option<QuLog>, QuLogError> {
+        self.update_at(id, update, Utc::now()).await
+    }
+
+    /// Attaches a file named `name` holding `data` to the log with `id`,
+    /// and returns the id of the attachment.
+    ///
+    /// ```
+    /// # use rand_sht::quick_logger::{QuLog, QuLogStore};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), rand_sht::quick_logger::QuLogError> {
+    /// let store = QuLogStore::in_memory().await?;
+    /// let log_id = store.insert(&QuLog::new("build failed", ["ci"])).await?;
+    ///
+    /// let id = store.attach(log_id, "build.log", b"error[E0308]".to_vec()).await?;
+    /// let log = store.get(log_id).await?.unwrap();
+    /// assert_eq!(log.attachments[0].name, "build.log");
+    ///
+    /// let (_, data) = store.attachment(id).await?.unwrap();
+    /// assert_eq!(data, b"error[E0308]");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn attach(
+        &self,
+        log_id: i64,
+        name: impl Into<String>,
+        data: Vec<u8>,
+    ) -> Result<i64, QuLogError> {
+        let file = QuLogAttachmentFile {
+            name: name.into(),
+            data,
+        };
+        let mut conn = self.pool.acquire().await?;
+        Ok(attachment::insert_attachment(&mut conn, log_id, &file).await?)
+    }
+
+    /// The attachment with `id` and its content, if there is one.
+    pub async fn attachment(
+        &self,
+        id: i64,
+    ) -> Result<Option<(QuLogAttachment, Vec<u8>)>, QuLogError> {
+        Ok(attachment::fetch_attachment(&self.pool, id).await?)
+    }
+
+    /// `update` as if it happened at `now`.
+    pub(super) async fn update_at(
+        &self,
+        id: i64,
+        update: &QuLogUpdate,
+        now: DateTime<Utc>,
+    ) -> Result<Option<QuLog>, QuLogError> {
+        Ok(update_log(&self.pool, id, update, now).await?)
+    }
+}
+
+impl LogStore for QuLogStore {
+    fn insert<'a>(&'a self, log: &'a QuLog) -> StoreFuture<'a, i64> {
+        Box::pin(QuLogStore::insert(self, log))
+    }
+
+    fn query<'a>(&'a self, filter: &'a QuLogFilter) -> StoreFuture<'a, Vec<QuLog>> {
+        Box::pin(QuLogStore::query(self, filter))
+    }
+
+    fn fetch<'a>(&'a self, filter: &'a QuLogFilter) -> LogStream<'a> {
+        QuLogStore::fetch(self, filter)
+    }
+
+    fn count<'a>(&'a self, filter: &'a QuLogFilter) -> StoreFuture<'a, i64> {
+        Box::pin(QuLogStore::count(self, filter))
+    }
+
+    fn delete<'a>(&'a self, filter: &'a QuLogFilter) -> StoreFuture<'a, u64> {
+        Box::pin(QuLogStore::delete(self, filter))
+    }
+
+    fn update_at<'a>(
+        &'a self,
+        id: i64,
+        update: &'a QuLogUpdate,
+        now: DateTime<Utc>,
+    ) -> StoreFuture<'a, Option<QuLog>> {
+        Box::pin(QuLogStore::update_at(self, id, update, now))
+    }
+
+    fn as_sqlite(&self) -> Option<&QuLogStore> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::*;
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[tokio::test]
+    async fn test_store_round_trip() -> Result<(), QuLogError> {
+        let store = QuLogStore::in_memory().await?;
+
+        let mut log = QuLog::new("written in tokyo", ["travel"]);
+        log.create_date = FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 4, 2, 8, 0, 0)
+            .unwrap();
+        let id = store.insert(&log).await?;
+
+        let saved = store.get(id).await?.unwrap();
+        assert_eq!(saved.id, Some(id));
+        assert_eq!(saved.text, log.text);
+        assert_eq!(saved.tags.0, vec!["travel"]);
+        assert_eq!(saved.create_date, log.create_date);
+        assert!(store.get(id + 1).await?.is_none());
+
+        let filter = QuLogFilter::builder()
+            .start_date(Utc.with_ymd_and_hms(2025, 4, 1, 23, 0, 0).unwrap())
+            .end_date(Utc.with_ymd_and_hms(2025, 4, 2, 0, 0, 0).unwrap())
+            .build();
+        assert_eq!(store.count(&filter).await?, 1);
+        assert_eq!(store.delete(&filter).await?, 1);
+        assert!(store.query(&QuLogFilter::default()).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stores_ignore_updates_changing_nothing() -> Result<(), QuLogError> {
+        let stores: [Box<dyn LogStore>; 2] = [
+            Box::new(QuLogStore::in_memory().await?),
+            Box::new(MemoryLogStore::new()),
+        ];
+
+        for store in stores {
+            let id = store.insert(&QuLog::new("same", ["work"])).await?;
+            let update = QuLogUpdate {
+                text: Some("same".to_string()),
+                add_tags: vec!["work".to_string()].into(),
+                ..Default::default()
+            };
+
+            let log = store.update_at(id, &update, Utc::now()).await?.unwrap();
+            assert_eq!(log.text, "same");
+            assert_eq!(log.updated_date, None);
+            let logs = store.query(&QuLogFilter::default()).await?;
+            assert_eq!(logs[0].updated_date, None);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_store_can_be_shared_across_tasks() -> Result<(), QuLogError> {
+        let store = QuLogStore::in_memory().await?;
+        let log = QuLog::new("from a task", ["spawned"]);
+
+        let insert = {
+            let store = store.clone();
+            async move { store.insert(&log).await }
+        };
+        assert_send(&insert);
+        tokio::spawn(insert).await.unwrap()?;
+
+        let filter = QuLogFilter::default();
+        let query = store.query(&filter);
+        assert_send(&query);
+        assert_eq!(query.await?.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use serde::Deserialize;
+
+use super::super::{error::QuLogError, export::QuLogRecord, QuLog, QuLogFilter};
+use super::{LogStore, StoreFuture};
+
+/// Logs appended to a file, one JSON object per line like `export --format ndjson` writes them,
+/// for people who'd rather grep a text file than open a database.
+/// Lines are never rewritten, so logs can't be edited or deleted. The id of a log is its line
+/// number, or the id the line holds like in an ndjson export, and new ones are kept above both.
+/// A file where two lines end up with the same id can't be read.
+#[derive(Debug)]
+pub struct JsonlLogStore {
+    path: PathBuf,
+    /// Held while appending so two inserts can't pick the same id
+    append: Mutex<()>,
+}
+
+impl JsonlLogStore {
+    /// Store writing to `path`, the file is created by the first insert.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        JsonlLogStore {
+            path: path.into(),
+            append: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read_error(&self, err: std::io::Error) -> QuLogError {
+        QuLogError::io(format!("Unable to read {}", self.path.display()), err)
+    }
+
+    fn read_logs(&self) -> Result<Vec<QuLog>, QuLogError> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(self.read_error(err)),
+        };
+
+        // Line each id was read from
+        let mut lines_by_id = HashMap::new();
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                let line_no = index as i64 + 1;
+                let invalid = |err: String| {
+                    QuLogError::Parse(format!("{} line {}: {}", self.path.display(), line_no, err))
+                };
+                let record: QuLogRecord =
+                    serde_json::from_str(line).map_err(|err| invalid(err.to_string()))?;
+                let id = record.id.unwrap_or(line_no);
+                if let Some(first) = lines_by_id.insert(id, line_no) {
+                    return Err(invalid(format!(
+                        "id {} is already used by line {}",
+                        id, first
+                    )));
+                }
+                let log = QuLog::try_from(record).map_err(invalid)?;
+                Ok(QuLog {
+                    id: Some(id),
+                    ..log
+                })
+            })
+            .collect()
+    }
+
+    /// Id of the next log, past the number of lines and every id they hold.
+    /// Lines that can't be read are counted but don't fail the insert, only reads.
+    fn next_id(&self) -> Result<i64, QuLogError> {
+        #[derive(Deserialize)]
+        struct RecordId {
+            id: Option<i64>,
+        }
+
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(1),
+            Err(err) => return Err(self.read_error(err)),
+        };
+        let max_id = content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<RecordId>(line).ok()?.id)
+            .max()
+            .unwrap_or(0);
+        Ok(max_id.max(content.lines().count() as i64) + 1)
+    }
+
+    fn append(&self, log: &QuLog) -> Result<i64, QuLogError> {
+        let _guard = self.append.lock().unwrap_or_else(PoisonError::into_inner);
+        let write_error =
+            |err| QuLogError::io(format!("Unable to write {}", self.path.display()), err);
+
+        let id = self.next_id()?;
+
+        let record = QuLogRecord::from(&QuLog {
+            id: Some(id),
+            ..log.clone()
+        });
+        let line = serde_json::to_string(&record)
+            .map_err(|err| QuLogError::Validation(format!("Unable to serialize log: {}", err)))?;
+
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(write_error)?;
+        writeln!(file, "{}", line).map_err(write_error)?;
+        Ok(id)
+    }
+}
+
+impl LogStore for JsonlLogStore {
+    fn insert<'a>(&'a self, log: &'a QuLog) -> StoreFuture<'a, i64> {
+        Box::pin(std::future::ready(self.append(log)))
+    }
+
+    fn query<'a>(&'a self, filter: &'a QuLogFilter) -> StoreFuture<'a, Vec<QuLog>> {
+        let logs = self.read_logs().map(|logs| filter.select(logs));
+        Box::pin(std::future::ready(logs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone, Utc};
+
+    use super::super::super::QuLogUpdate;
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("qulog-jsonl-{}", std::process::id()));
+        let path = dir.join(name);
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_store_round_trip() {
+        let path = temp_path("round_trip.jsonl");
+        let store = JsonlLogStore::new(&path);
+        assert!(store
+            .query(&QuLogFilter::default())
+            .await
+            .unwrap()
+            .is_empty());
+
+        let mut log = QuLog::new("landed in tokyo", ["travel"]);
+        log.create_date = FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 4, 2, 8, 0, 0)
+            .unwrap();
+        assert_eq!(store.insert(&log).await.unwrap(), 1);
+        assert_eq!(
+            store
+                .insert(&QuLog::new("unpacked", ["home"]))
+                .await
+                .unwrap(),
+            2
+        );
+
+        // Read back by a new store, like the next qulog run would
+        let store = JsonlLogStore::new(&path);
+        let logs = store.query(&QuLogFilter::default()).await.unwrap();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].id, Some(1));
+        assert_eq!(logs[0].create_date, log.create_date);
+        assert_eq!(logs[0].create_date.offset(), log.create_date.offset());
+
+        let filter = QuLogFilter::builder()
+            .end_date(Utc.with_ymd_and_hms(2025, 4, 2, 0, 0, 0).unwrap())
+            .build();
+        assert_eq!(store.count(&filter).await.unwrap(), 1);
+
+        assert!(matches!(
+            store.delete(&filter).await,
+            Err(QuLogError::Validation(_))
+        ));
+        assert!(matches!(
+            store
+                .update_at(1, &QuLogUpdate::default(), Utc::now())
+                .await,
+            Err(QuLogError::Validation(_))
+        ));
+        assert!(store.as_sqlite().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_store_keeps_exported_ids() {
+        // Seeded from `export --format ndjson`, ids come from the database
+        let path = temp_path("exported.jsonl");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &path,
+            "{\"id\":40,\"text\":\"a\",\"create_date\":\"2025-04-02T08:00:00+00:00\"}\n\
+             {\"id\":7,\"text\":\"b\",\"create_date\":\"2025-04-02T09:00:00+00:00\"}\n",
+        )
+        .unwrap();
+
+        let store = JsonlLogStore::new(&path);
+        assert_eq!(store.insert(&QuLog::new("c", ["new"])).await.unwrap(), 41);
+        assert_eq!(store.insert(&QuLog::new("d", ["new"])).await.unwrap(), 42);
+
+        let mut ids: Vec<i64> = store
+            .query(&QuLogFilter::default())
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|log| log.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![7, 40, 41, 42]);
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_store_duplicate_ids() {
+        // The second line would be log 2 by its line number, which the first one already is
+        let path = temp_path("duplicate.jsonl");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &path,
+            "{\"id\":2,\"text\":\"a\",\"create_date\":\"2025-04-02T08:00:00+00:00\"}\n\
+             {\"text\":\"b\",\"create_date\":\"2025-04-02T09:00:00+00:00\"}\n",
+        )
+        .unwrap();
+
+        let err = JsonlLogStore::new(&path)
+            .query(&QuLogFilter::default())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, QuLogError::Parse(message) if message.contains("line 2") && message.contains("line 1")),
+            "{:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_store_invalid_line() {
+        let path = temp_path("invalid.jsonl");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &path,
+            "{\"text\":\"ok\",\"create_date\":\"2025-04-02T08:00:00+00:00\"}\n\nnot json\n",
+        )
+        .unwrap();
+
+        let err = JsonlLogStore::new(&path)
+            .query(&QuLogFilter::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(&err, QuLogError::Parse(message) if message.contains("line 3")));
+    }
+}
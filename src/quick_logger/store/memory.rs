@@ -0,0 +1,146 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use chrono::{DateTime, Utc};
+
+use super::super::{QuLog, QuLogFilter, QuLogTags, QuLogUpdate};
+use super::{LogStore, StoreFuture};
+
+/// Logs kept in a `Vec`, for tests and callers that don't want anything written to disk.
+/// Ids start at 1 and are never reused, like SQLite's.
+#[derive(Debug, Default)]
+pub struct MemoryLogStore {
+    logs: Mutex<Vec<QuLog>>,
+    last_id: Mutex<i64>,
+}
+
+impl MemoryLogStore {
+    pub fn new() -> Self {
+        MemoryLogStore::default()
+    }
+
+    fn logs(&self) -> MutexGuard<'_, Vec<QuLog>> {
+        // A panic while holding the lock can't leave the Vec half written, keep using it
+        self.logs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl LogStore for MemoryLogStore {
+    fn insert<'a>(&'a self, log: &'a QuLog) -> StoreFuture<'a, i64> {
+        let mut last_id = self.last_id.lock().unwrap_or_else(PoisonError::into_inner);
+        *last_id += 1;
+        self.logs().push(QuLog {
+            id: Some(*last_id),
+            ..log.clone()
+        });
+        Box::pin(std::future::ready(Ok(*last_id)))
+    }
+
+    fn query<'a>(&'a self, filter: &'a QuLogFilter) -> StoreFuture<'a, Vec<QuLog>> {
+        let logs = filter.select(self.logs().iter().cloned());
+        Box::pin(std::future::ready(Ok(logs)))
+    }
+
+    fn delete<'a>(&'a self, filter: &'a QuLogFilter) -> StoreFuture<'a, u64> {
+        let mut logs = self.logs();
+        let selected: Vec<Option<i64>> = filter
+            .select(logs.iter().cloned())
+            .into_iter()
+            .map(|log| log.id)
+            .collect();
+        let before = logs.len();
+        logs.retain(|log| !selected.contains(&log.id));
+        let deleted = (before - logs.len()) as u64;
+        Box::pin(std::future::ready(Ok(deleted)))
+    }
+
+    fn update_at<'a>(
+        &'a self,
+        id: i64,
+        update: &'a QuLogUpdate,
+        now: DateTime<Utc>,
+    ) -> StoreFuture<'a, Option<QuLog>> {
+        let updated = self
+            .logs()
+            .iter_mut()
+            .find(|log| log.id == Some(id))
+            .map(|log| {
+                let (text, tags) = update.apply(log);
+                if text == log.text && tags == log.tags.0 {
+                    return log.clone();
+                }
+                log.text = text;
+                log.tags = QuLogTags(tags);
+                log.updated_date = Some(now);
+                log.clone()
+            });
+        Box::pin(std::future::ready(Ok(updated)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::super::super::QuLogTagMatch;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        let store = MemoryLogStore::new();
+        let mut log = QuLog::new("standup", ["work", "meeting"]);
+        log.create_date = Utc
+            .with_ymd_and_hms(2025, 3, 10, 9, 0, 0)
+            .unwrap()
+            .fixed_offset();
+        assert_eq!(store.insert(&log).await.unwrap(), 1);
+        log.create_date = Utc
+            .with_ymd_and_hms(2025, 3, 11, 9, 0, 0)
+            .unwrap()
+            .fixed_offset();
+        assert_eq!(store.insert(&log).await.unwrap(), 2);
+        assert_eq!(
+            store.insert(&QuLog::new("lunch", ["food"])).await.unwrap(),
+            3
+        );
+
+        let filter = QuLogFilter::builder()
+            .tags(["meeting", "food"])
+            .tag_match(QuLogTagMatch::Any)
+            .end_date(Utc.with_ymd_and_hms(2025, 3, 10, 9, 0, 1).unwrap())
+            .build();
+        let logs = store.query(&filter).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].id, Some(1));
+
+        // The end is exclusive, like in SQL
+        let filter = QuLogFilter::builder()
+            .end_date(Utc.with_ymd_and_hms(2025, 3, 10, 9, 0, 0).unwrap())
+            .build();
+        assert_eq!(store.count(&filter).await.unwrap(), 0);
+
+        let update = QuLogUpdate {
+            add_tags: vec!["done".to_string()].into(),
+            remove_tags: vec!["meeting".to_string()].into(),
+            ..Default::default()
+        };
+        let now = Utc.with_ymd_and_hms(2025, 3, 12, 0, 0, 0).unwrap();
+        let updated = store.update_at(2, &update, now).await.unwrap().unwrap();
+        assert_eq!(updated.tags.0, vec!["work", "done"]);
+        store.update_at(3, &update, now).await.unwrap();
+        assert_eq!(updated.updated_date, Some(now));
+        assert!(store.update_at(7, &update, now).await.unwrap().is_none());
+
+        let done = QuLogFilter::builder().tags(["done"]).build();
+        assert_eq!(store.delete(&done).await.unwrap(), 2);
+        assert_eq!(store.count(&QuLogFilter::default()).await.unwrap(), 1);
+
+        // Ids aren't reused once a log is deleted
+        assert_eq!(
+            store
+                .insert(&QuLog::new("later", Vec::<String>::new()))
+                .await
+                .unwrap(),
+            4
+        );
+    }
+}
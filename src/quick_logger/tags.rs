@@ -0,0 +1,379 @@
+use chrono::{DateTime, Utc};
+use sqlx::{sqlite::SqlitePool, FromRow, QueryBuilder, Sqlite, SqliteConnection};
+
+use super::{delete_unused_tags, error::QuLogError, fetch_log, insert_edit, QuLog};
+
+/// A tag and the number of logs having it.
+#[derive(Debug, PartialEq, Eq, FromRow)]
+pub(super) struct QuLogTagUsage {
+    pub(super) name: String,
+    pub(super) count: i64,
+}
+
+/// Parses a tag given on the command line, it can't be empty or hold a comma
+/// since tags are written comma-joined.
+pub(super) fn parse_tag(value: &str) -> Result<String, String> {
+    let tag = value.trim();
+    if tag.is_empty() || tag.contains(',') {
+        return Err(format!("invalid tag {:?}", value));
+    }
+    Ok(tag.to_string())
+}
+
+/// Every tag with the number of logs having it, most used first.
+pub(super) async fn list_tags(pool: &SqlitePool) -> Result<Vec<QuLogTagUsage>, sqlx::Error> {
+    sqlx::query_as::<Sqlite, QuLogTagUsage>(
+        r#"
+        SELECT tag.name, COUNT(*) AS count FROM tag
+        JOIN qu_log_tag ON qu_log_tag.tag_id = tag.id
+        GROUP BY tag.id ORDER BY count DESC, tag.name
+    "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Renames `old` to `new` on every log having it, keeping its place among their tags.
+/// Returns the number of logs changed.
+pub(super) async fn rename_tag(
+    pool: &SqlitePool,
+    old: &str,
+    new: &str,
+    now: DateTime<Utc>,
+) -> Result<u64, QuLogError> {
+    let mut tx = pool.begin().await?;
+
+    let id = existing_tag_id(&mut tx, old).await?;
+    if old == new {
+        return Err(QuLogError::Validation(format!(
+            "{} and {} are the same tag",
+            old, new
+        )));
+    }
+    if tag_id(&mut tx, new).await?.is_some() {
+        return Err(QuLogError::Validation(format!(
+            "Tag {} already exists, use qulog tags merge {} --into {}",
+            new, old, new
+        )));
+    }
+
+    let logs = tagged_logs(&mut tx, &[id]).await?;
+    sqlx::query("UPDATE tag SET name = $1 WHERE id = $2")
+        .bind(new)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    record_edits(&mut tx, &logs, now).await?;
+
+    tx.commit().await?;
+    Ok(logs.len() as u64)
+}
+
+/// Replaces `tags` by `into` on every log having one of them, `into` takes the place of
+/// one of them and is created when no log has it yet. Returns the number of logs changed.
+pub(super) async fn merge_tags(
+    pool: &SqlitePool,
+    tags: &[String],
+    into: &str,
+    now: DateTime<Utc>,
+) -> Result<u64, QuLogError> {
+    let mut tx = pool.begin().await?;
+
+    let mut ids = Vec::new();
+    for tag in tags.iter().filter(|tag| *tag != into) {
+        ids.push(existing_tag_id(&mut tx, tag).await?);
+    }
+    if ids.is_empty() {
+        return Err(QuLogError::Validation(format!(
+            "No tag other than {} is given to merge",
+            into
+        )));
+    }
+
+    let logs = tagged_logs(&mut tx, &ids).await?;
+    sqlx::query("INSERT OR IGNORE INTO tag (name) VALUES ($1)")
+        .bind(into)
+        .execute(&mut *tx)
+        .await?;
+    let into_id: i64 = sqlx::query_scalar("SELECT id FROM tag WHERE name = $1")
+        .bind(into)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    // Links of logs already having `into` are ignored here and deleted below
+    let mut query = QueryBuilder::<Sqlite>::new("UPDATE OR IGNORE qu_log_tag SET tag_id = ");
+    query.push_bind(into_id);
+    push_tag_ids(&mut query, &ids);
+    query.build().execute(&mut *tx).await?;
+
+    let mut query = QueryBuilder::<Sqlite>::new("DELETE FROM qu_log_tag");
+    push_tag_ids(&mut query, &ids);
+    query.build().execute(&mut *tx).await?;
+
+    delete_unused_tags(&mut tx).await?;
+    record_edits(&mut tx, &logs, now).await?;
+
+    tx.commit().await?;
+    Ok(logs.len() as u64)
+}
+
+/// Removes `tag` from every log having it, the logs are kept. Returns the number of logs changed.
+pub(super) async fn delete_tag(
+    pool: &SqlitePool,
+    tag: &str,
+    now: DateTime<Utc>,
+) -> Result<u64, QuLogError> {
+    let mut tx = pool.begin().await?;
+
+    let id = existing_tag_id(&mut tx, tag).await?;
+    let logs = tagged_logs(&mut tx, &[id]).await?;
+    sqlx::query("DELETE FROM qu_log_tag WHERE tag_id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    delete_unused_tags(&mut tx).await?;
+    record_edits(&mut tx, &logs, now).await?;
+
+    tx.commit().await?;
+    Ok(logs.len() as u64)
+}
+
+async fn tag_id(conn: &mut SqliteConnection, name: &str) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM tag WHERE name = $1")
+        .bind(name)
+        .fetch_optional(conn)
+        .await
+}
+
+async fn existing_tag_id(conn: &mut SqliteConnection, name: &str) -> Result<i64, QuLogError> {
+    tag_id(conn, name)
+        .await?
+        .ok_or_else(|| QuLogError::Validation(format!("No tag named {} is found", name)))
+}
+
+fn push_tag_ids(query: &mut QueryBuilder<Sqlite>, ids: &[i64]) {
+    query.push(" WHERE tag_id IN (");
+    let mut separated = query.separated(", ");
+    for id in ids {
+        separated.push_bind(*id);
+    }
+    separated.push_unseparated(")");
+}
+
+/// Logs having one of the tags with `ids`, as they are before the tags change.
+async fn tagged_logs(conn: &mut SqliteConnection, ids: &[i64]) -> Result<Vec<QuLog>, sqlx::Error> {
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT DISTINCT log_id FROM qu_log_tag");
+    push_tag_ids(&mut query, ids);
+    query.push(" ORDER BY log_id");
+    let log_ids: Vec<i64> = query.build_query_scalar().fetch_all(&mut *conn).await?;
+
+    let mut logs = Vec::with_capacity(log_ids.len());
+    for id in log_ids {
+        logs.extend(fetch_log(conn, id).await?);
+    }
+    Ok(logs)
+}
+
+/// Keeps the tags `logs` had before in their edit history, like `qulog edit` does.
+async fn record_edits(
+    conn: &mut SqliteConnection,
+    logs: &[QuLog],
+    now: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    for old in logs {
+        let Some(new) = fetch_log(conn, old.id.unwrap_or_default()).await? else {
+            continue;
+        };
+        sqlx::query("UPDATE qu_log SET updated_date = $1 WHERE id = $2")
+            .bind(now)
+            .bind(new.id)
+            .execute(&mut *conn)
+            .await?;
+        insert_edit(conn, old, &new.text, &new.tags.0, now).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{connect_to_db, create_log, fetch_log_edits, fetch_logs, migrations};
+    use super::super::{DBConfig, QuLogFilter};
+    use super::*;
+
+    async fn pool_with_logs(logs: &[(&str, &[&str])]) -> SqlitePool {
+        let pool = connect_to_db(&DBConfig::in_memory()).await.unwrap();
+        migrations::migrate(&pool).await.unwrap();
+        for (text, tags) in logs {
+            let log = QuLog::new(*text, tags.iter().copied());
+            create_log(&log, &pool).await.unwrap();
+        }
+        pool
+    }
+
+    async fn tags_by_text(pool: &SqlitePool) -> Vec<(String, String)> {
+        fetch_logs(pool, &QuLogFilter::default())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|log| (log.text, log.tags.0.join(",")))
+            .collect()
+    }
+
+    async fn tag_names(pool: &SqlitePool) -> Vec<String> {
+        sqlx::query_scalar("SELECT name FROM tag ORDER BY name")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected
+            .iter()
+            .map(|(text, tags)| (text.to_string(), tags.to_string()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_list_tags() {
+        let pool = pool_with_logs(&[
+            ("one", &["work", "rust"]),
+            ("two", &["rust"]),
+            ("three", &["ops"]),
+        ])
+        .await;
+
+        let usages = list_tags(&pool).await.unwrap();
+        let usages: Vec<(&str, i64)> = usages
+            .iter()
+            .map(|usage| (usage.name.as_str(), usage.count))
+            .collect();
+        assert_eq!(usages, vec![("rust", 2), ("ops", 1), ("work", 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_rename_tag() {
+        let pool = pool_with_logs(&[
+            ("one", &["wrok", "rust"]),
+            ("two", &["rust"]),
+            ("three", &["ops", "wrok"]),
+        ])
+        .await;
+
+        let changed = rename_tag(&pool, "wrok", "work", Utc::now()).await.unwrap();
+        assert_eq!(changed, 2);
+        assert_eq!(
+            tags_by_text(&pool).await,
+            pairs(&[("one", "work,rust"), ("two", "rust"), ("three", "ops,work")])
+        );
+
+        let logs = fetch_logs(&pool, &QuLogFilter::default()).await.unwrap();
+        assert!(logs[0].updated_date.is_some());
+        assert!(logs[1].updated_date.is_none());
+        let edits = fetch_log_edits(&pool, logs[0].id.unwrap()).await.unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].old_tags, "wrok,rust");
+        assert_eq!(edits[0].new_tags, "work,rust");
+
+        // Renaming to a tag in use would merge them, which is left to `merge`
+        assert!(matches!(
+            rename_tag(&pool, "ops", "rust", Utc::now()).await,
+            Err(QuLogError::Validation(_))
+        ));
+        assert!(matches!(
+            rename_tag(&pool, "work", "work", Utc::now()).await,
+            Err(QuLogError::Validation(_))
+        ));
+        assert_eq!(tag_names(&pool).await, vec!["ops", "rust", "work"]);
+    }
+
+    #[tokio::test]
+    async fn test_merge_tags() {
+        let pool = pool_with_logs(&[
+            ("one", &["js", "web"]),
+            ("two", &["ts", "js"]),
+            ("three", &["rust"]),
+            ("four", &["web", "javascript"]),
+        ])
+        .await;
+
+        let tags = ["js".to_string(), "ts".to_string()];
+        let changed = merge_tags(&pool, &tags, "javascript", Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(changed, 2);
+        assert_eq!(
+            tags_by_text(&pool).await,
+            pairs(&[
+                ("one", "javascript,web"),
+                ("two", "javascript"),
+                ("three", "rust"),
+                ("four", "web,javascript"),
+            ])
+        );
+        assert_eq!(tag_names(&pool).await, vec!["javascript", "rust", "web"]);
+
+        // Merging into a new tag creates it, `into` among the merged tags is skipped
+        let tags = ["web".to_string(), "frontend".to_string()];
+        let changed = merge_tags(&pool, &tags[..1], "frontend", Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(changed, 2);
+        assert!(matches!(
+            merge_tags(&pool, &tags[1..], "frontend", Utc::now()).await,
+            Err(QuLogError::Validation(_))
+        ));
+        assert_eq!(
+            tag_names(&pool).await,
+            vec!["frontend", "javascript", "rust"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_unknown_tag_changes_nothing() {
+        let pool = pool_with_logs(&[("one", &["js"]), ("two", &["ts"])]).await;
+
+        let tags = ["js".to_string(), "typo".to_string()];
+        assert!(matches!(
+            merge_tags(&pool, &tags, "ts", Utc::now()).await,
+            Err(QuLogError::Validation(_))
+        ));
+        assert_eq!(
+            tags_by_text(&pool).await,
+            pairs(&[("one", "js"), ("two", "ts")])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_tag() {
+        let pool = pool_with_logs(&[
+            ("one", &["draft", "rust"]),
+            ("two", &["draft"]),
+            ("three", &["rust"]),
+        ])
+        .await;
+
+        assert_eq!(delete_tag(&pool, "draft", Utc::now()).await.unwrap(), 2);
+        assert_eq!(
+            tags_by_text(&pool).await,
+            pairs(&[("one", "rust"), ("two", ""), ("three", "rust")])
+        );
+        assert_eq!(tag_names(&pool).await, vec!["rust"]);
+
+        let logs = fetch_logs(&pool, &QuLogFilter::default()).await.unwrap();
+        let edits = fetch_log_edits(&pool, logs[1].id.unwrap()).await.unwrap();
+        assert_eq!(edits[0].old_tags, "draft");
+        assert_eq!(edits[0].new_tags, "");
+
+        assert!(matches!(
+            delete_tag(&pool, "draft", Utc::now()).await,
+            Err(QuLogError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_tag() {
+        assert_eq!(parse_tag(" rust "), Ok("rust".to_string()));
+        assert!(parse_tag("").is_err());
+        assert!(parse_tag("a,b").is_err());
+    }
+}
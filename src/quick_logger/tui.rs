@@ -0,0 +1,649 @@
+use std::io::IsTerminal;
+
+use chrono::Weekday;
+use clap::ValueEnum;
+use ratatui::{
+    backend::Backend,
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+
+use super::{
+    clock::Clock, dates::QuLogDateDisplay, error::QuLogError, format_entry, ranges::QuLogDateRange,
+    store::LogStore, QuLog, QuLogFilter, QuLogSort, QuLogUpdate,
+};
+
+/// Rows `PageUp` and `PageDown` move by
+const PAGE: usize = 10;
+
+/// What the keys currently do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    Browse,
+    /// Typing in the search box, the logs are filtered as it changes
+    Search,
+    /// Changing the text of the selected log
+    Edit(String),
+    /// Waiting for `y` to delete the selected log
+    ConfirmDelete,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Focus {
+    Logs,
+    Tags,
+}
+
+/// State of `qulog tui`: logs of a date range, newest first, narrowed by the tags picked
+/// in the sidebar and by the search box.
+pub(super) struct QuLogTui<'a> {
+    store: &'a dyn LogStore,
+    clock: &'a dyn Clock,
+    week_start: Weekday,
+    display: QuLogDateDisplay,
+    /// Presets `d` cycles through, `None` is every log
+    ranges: Vec<Option<QuLogDateRange>>,
+    range: usize,
+    /// Logs of the range having every selected tag
+    logs: Vec<QuLog>,
+    /// Tags used in the range, by name, with how many logs have them
+    tags: Vec<(String, usize)>,
+    selected_tags: Vec<String>,
+    search: String,
+    mode: Mode,
+    focus: Focus,
+    log_state: ListState,
+    tag_state: ListState,
+    /// Outcome of the last action, shown instead of the key help
+    status: String,
+    quit: bool,
+}
+
+impl<'a> QuLogTui<'a> {
+    pub(super) async fn new(
+        store: &'a dyn LogStore,
+        clock: &'a dyn Clock,
+        week_start: Weekday,
+        display: QuLogDateDisplay,
+    ) -> Result<Self, QuLogError> {
+        let ranges = std::iter::once(None)
+            .chain(
+                QuLogDateRange::value_variants()
+                    .iter()
+                    .filter(|range| **range != QuLogDateRange::LastNDays)
+                    .map(|range| Some(*range)),
+            )
+            .collect();
+
+        let mut tui = QuLogTui {
+            store,
+            clock,
+            week_start,
+            display,
+            ranges,
+            range: 0,
+            logs: Vec::new(),
+            tags: Vec::new(),
+            selected_tags: Vec::new(),
+            search: String::new(),
+            mode: Mode::Browse,
+            focus: Focus::Logs,
+            log_state: ListState::default(),
+            tag_state: ListState::default(),
+            status: String::new(),
+            quit: false,
+        };
+        tui.reload().await?;
+        Ok(tui)
+    }
+
+    /// Draws and handles keys until `q` is pressed.
+    pub(super) async fn run<B: Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+    ) -> Result<(), QuLogError> {
+        while !self.quit {
+            terminal
+                .draw(|frame| self.render(frame))
+                .map_err(|err| QuLogError::io("Unable to draw the terminal", err))?;
+
+            let event =
+                event::read().map_err(|err| QuLogError::io("Unable to read the terminal", err))?;
+            if let Event::Key(key) = event {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn range_name(&self) -> String {
+        match self.ranges[self.range] {
+            Some(range) => range
+                .to_possible_value()
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default(),
+            None => "all".to_string(),
+        }
+    }
+
+    /// Queries the logs of the range again, after it, the selected tags or a log changed.
+    async fn reload(&mut self) -> Result<(), QuLogError> {
+        let mut filter = QuLogFilter::builder().sort(QuLogSort::Desc);
+        if let Some(range) = self.ranges[self.range] {
+            let span = range
+                .span(None, self.clock, self.week_start)
+                .map_err(QuLogError::Validation)?;
+            filter = filter.start_date(span.start).end_date(span.end);
+        }
+
+        let mut tags: Vec<(String, usize)> = Vec::new();
+        for log in self.store.query(&filter.clone().build()).await? {
+            for tag in log.tags.0 {
+                match tags.iter_mut().find(|(name, _)| *name == tag) {
+                    Some((_, count)) => *count += 1,
+                    None => tags.push((tag, 1)),
+                }
+            }
+        }
+        tags.sort();
+        self.tags = tags;
+        // A tag missing from the range would hide every log without showing why
+        self.selected_tags
+            .retain(|tag| self.tags.iter().any(|(name, _)| name == tag));
+
+        self.logs = self
+            .store
+            .query(&filter.tags(self.selected_tags.clone()).build())
+            .await?;
+
+        let visible = self.visible().len();
+        self.log_state
+            .select((visible > 0).then(|| self.log_state.selected().unwrap_or(0).min(visible - 1)));
+        self.tag_state.select((!self.tags.is_empty()).then(|| {
+            self.tag_state
+                .selected()
+                .unwrap_or(0)
+                .min(self.tags.len() - 1)
+        }));
+        Ok(())
+    }
+
+    /// Logs matching the search box, by text or tag, whatever the case.
+    fn visible(&self) -> Vec<&QuLog> {
+        let search = self.search.to_lowercase();
+        self.logs
+            .iter()
+            .filter(|log| {
+                log.text.to_lowercase().contains(&search)
+                    || log
+                        .tags
+                        .0
+                        .iter()
+                        .any(|tag| tag.to_lowercase().contains(&search))
+            })
+            .collect()
+    }
+
+    fn selected_log(&self) -> Option<&QuLog> {
+        self.log_state
+            .selected()
+            .and_then(|index| self.visible().get(index).copied())
+    }
+
+    /// Runs a store action, its error ends up in the status line instead of closing the tui.
+    fn report(&mut self, result: Result<String, QuLogError>) {
+        self.status = match result {
+            Ok(status) => status,
+            Err(err) => err.to_string(),
+        };
+    }
+
+    pub(super) async fn handle_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+        self.status.clear();
+
+        match self.mode.clone() {
+            Mode::Browse => self.browse(key).await,
+            Mode::Search => match key.code {
+                KeyCode::Enter => self.mode = Mode::Browse,
+                KeyCode::Esc => {
+                    self.search.clear();
+                    self.mode = Mode::Browse;
+                }
+                KeyCode::Backspace => {
+                    self.search.pop();
+                }
+                KeyCode::Char(c) => self.search.push(c),
+                _ => {}
+            },
+            Mode::Edit(mut text) => match key.code {
+                KeyCode::Enter => {
+                    self.mode = Mode::Browse;
+                    let result = self.save(text).await;
+                    self.report(result);
+                }
+                KeyCode::Esc => self.mode = Mode::Browse,
+                KeyCode::Backspace => {
+                    text.pop();
+                    self.mode = Mode::Edit(text);
+                }
+                KeyCode::Char(c) => {
+                    text.push(c);
+                    self.mode = Mode::Edit(text);
+                }
+                _ => {}
+            },
+            Mode::ConfirmDelete => {
+                self.mode = Mode::Browse;
+                if key.code == KeyCode::Char('y') {
+                    let result = self.delete().await;
+                    self.report(result);
+                }
+            }
+        }
+
+        // The search may have hidden the selected log, or shown the first one
+        let visible = self.visible().len();
+        match self.log_state.selected() {
+            None if visible > 0 => self.log_state.select(Some(0)),
+            Some(index) if index >= visible => self.log_state.select(visible.checked_sub(1)),
+            _ => {}
+        }
+    }
+
+    async fn browse(&mut self, key: KeyEvent) {
+        let len = match self.focus {
+            Focus::Logs => self.visible().len(),
+            Focus::Tags => self.tags.len(),
+        };
+        let state = match self.focus {
+            Focus::Logs => &mut self.log_state,
+            Focus::Tags => &mut self.tag_state,
+        };
+        let last = len.saturating_sub(1);
+        let current = state.selected().unwrap_or(0);
+        let moved = match key.code {
+            KeyCode::Down | KeyCode::Char('j') => Some((current + 1).min(last)),
+            KeyCode::Up | KeyCode::Char('k') => Some(current.saturating_sub(1)),
+            KeyCode::PageDown => Some((current + PAGE).min(last)),
+            KeyCode::PageUp => Some(current.saturating_sub(PAGE)),
+            KeyCode::Home | KeyCode::Char('g') => Some(0),
+            KeyCode::End | KeyCode::Char('G') => Some(last),
+            _ => None,
+        };
+        if let Some(index) = moved {
+            state.select((len > 0).then_some(index));
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc if !self.search.is_empty() => self.search.clear(),
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Logs => Focus::Tags,
+                    Focus::Tags => Focus::Logs,
+                }
+            }
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Char('d') | KeyCode::Char('D') => {
+                let count = self.ranges.len();
+                self.range = match key.code {
+                    KeyCode::Char('d') => (self.range + 1) % count,
+                    _ => (self.range + count - 1) % count,
+                };
+                self.log_state.select(Some(0));
+                let result = self.reload().await.map(|_| String::new());
+                self.report(result);
+            }
+            KeyCode::Enter | KeyCode::Char(' ') if self.focus == Focus::Tags => {
+                let Some((tag, _)) = self.tag_state.selected().and_then(|i| self.tags.get(i))
+                else {
+                    return;
+                };
+                match self
+                    .selected_tags
+                    .iter()
+                    .position(|selected| selected == tag)
+                {
+                    Some(position) => {
+                        self.selected_tags.remove(position);
+                    }
+                    None => self.selected_tags.push(tag.clone()),
+                }
+                self.log_state.select(Some(0));
+                let result = self.reload().await.map(|_| String::new());
+                self.report(result);
+            }
+            KeyCode::Char('e') | KeyCode::Enter if self.focus == Focus::Logs => {
+                if let Some(log) = self.selected_log() {
+                    self.mode = Mode::Edit(log.text.clone());
+                }
+            }
+            KeyCode::Char('x') | KeyCode::Delete
+                if self.focus == Focus::Logs && self.selected_log().is_some() =>
+            {
+                self.mode = Mode::ConfirmDelete;
+            }
+            _ => {}
+        }
+    }
+
+    async fn save(&mut self, text: String) -> Result<String, QuLogError> {
+        let Some(id) = self.selected_log().and_then(|log| log.id) else {
+            return Ok(String::new());
+        };
+        if text.trim().is_empty() {
+            return Err(QuLogError::Validation("A log can't be empty".to_string()));
+        }
+
+        let update = QuLogUpdate {
+            text: Some(text),
+            ..Default::default()
+        };
+        self.store
+            .update_at(id, &update, self.clock.now().to_utc())
+            .await?;
+        self.reload().await?;
+        Ok(format!("Log {} saved", id))
+    }
+
+    async fn delete(&mut self) -> Result<String, QuLogError> {
+        let Some(id) = self.selected_log().and_then(|log| log.id) else {
+            return Ok(String::new());
+        };
+
+        self.store
+            .delete(&QuLogFilter::builder().ids([id]).build())
+            .await?;
+        self.reload().await?;
+        Ok(format!("Log {} deleted", id))
+    }
+
+    pub(super) fn render(&mut self, frame: &mut Frame) {
+        let [main, input, help_area] = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [tags_area, logs_area] =
+            Layout::horizontal([Constraint::Length(24), Constraint::Min(20)]).areas(main);
+
+        let focused = |focus: Focus| {
+            if self.focus == focus {
+                Style::new().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::new().add_modifier(Modifier::BOLD)
+            }
+        };
+
+        let tags: Vec<ListItem> = self
+            .tags
+            .iter()
+            .map(|(tag, count)| {
+                let mark = if self.selected_tags.contains(tag) {
+                    "*"
+                } else {
+                    " "
+                };
+                ListItem::new(format!("{}{} ({})", mark, tag, count))
+            })
+            .collect();
+        let tags = List::new(tags)
+            .block(Block::bordered().title(" Tags "))
+            .highlight_style(focused(Focus::Tags));
+        frame.render_stateful_widget(tags, tags_area, &mut self.tag_state);
+
+        let visible = self.visible();
+        let title = format!(" Logs ({}) · {} ", visible.len(), self.range_name());
+        let logs: Vec<ListItem> = visible
+            .iter()
+            .map(|log| {
+                let prefix = format!(
+                    "[{}] {} : ",
+                    log.id.unwrap_or_default(),
+                    self.display.format(log.create_date)
+                );
+                let edited = if log.updated_date.is_some() {
+                    " (edited)"
+                } else {
+                    ""
+                };
+                ListItem::new(format_entry(&prefix, &log.text, &log.tags, edited))
+            })
+            .collect();
+        let logs = List::new(logs)
+            .block(Block::bordered().title(title))
+            .highlight_style(focused(Focus::Logs))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(logs, logs_area, &mut self.log_state);
+
+        let (title, text) = match &self.mode {
+            Mode::Edit(text) => (
+                format!(
+                    " Edit log {} ",
+                    self.selected_log()
+                        .and_then(|log| log.id)
+                        .unwrap_or_default()
+                ),
+                text.clone(),
+            ),
+            _ => (" Search ".to_string(), format!("/{}", self.search)),
+        };
+        frame.render_widget(
+            Paragraph::new(text).block(Block::bordered().title(title)),
+            input,
+        );
+
+        let help = match self.mode {
+            _ if !self.status.is_empty() => self.status.clone(),
+            Mode::Browse => {
+                "q quit · / search · tab tags · d range · e edit · x delete".to_string()
+            }
+            Mode::Search => "enter keep · esc clear".to_string(),
+            Mode::Edit(_) => "enter save · esc cancel".to_string(),
+            Mode::ConfirmDelete => format!(
+                "Delete log {}? y/n",
+                self.selected_log()
+                    .and_then(|log| log.id)
+                    .unwrap_or_default()
+            ),
+        };
+        frame.render_widget(Line::raw(help), help_area);
+    }
+}
+
+/// `qulog tui`, in the alternate screen of the terminal, which is restored on the way out.
+pub(super) async fn run(
+    store: &dyn LogStore,
+    clock: &dyn Clock,
+    week_start: Weekday,
+    display: QuLogDateDisplay,
+) -> Result<(), QuLogError> {
+    if !std::io::stdout().is_terminal() {
+        return Err(QuLogError::Validation(
+            "qulog tui needs a terminal".to_string(),
+        ));
+    }
+
+    let mut tui = QuLogTui::new(store, clock, week_start, display).await?;
+    let mut terminal = ratatui::init();
+    let result = tui.run(&mut terminal).await;
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Days, Local, TimeZone};
+    use ratatui::backend::TestBackend;
+
+    use super::super::{clock::FixedClock, dates::QuLogTimeZone, store::MemoryLogStore};
+    use super::*;
+
+    /// Wednesday 2025-05-14 at noon
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2025, 5, 14, 12, 0, 0).unwrap()
+    }
+
+    async fn store() -> MemoryLogStore {
+        let store = MemoryLogStore::new();
+        let logs = [
+            ("fixed the deploy script", vec!["work", "ops"], 30),
+            ("went climbing", vec!["sport"], 2),
+            ("code review", vec!["work"], 0),
+        ];
+        for (text, tags, days_ago) in logs {
+            let mut log = QuLog::new(text, tags);
+            log.create_date = (now() - Days::new(days_ago)).fixed_offset();
+            store.insert(&log).await.unwrap();
+        }
+        store
+    }
+
+    fn display() -> QuLogDateDisplay {
+        QuLogDateDisplay {
+            format: "%m-%d".to_string(),
+            tz: QuLogTimeZone::Local,
+        }
+    }
+
+    fn screen(tui: &mut QuLogTui) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(90, 12)).unwrap();
+        terminal.draw(|frame| tui.render(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    async fn press(tui: &mut QuLogTui<'_>, keys: &str) {
+        for c in keys.chars() {
+            tui.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .await;
+        }
+    }
+
+    async fn press_code(tui: &mut QuLogTui<'_>, code: KeyCode) {
+        tui.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_tui_lists_logs_and_tags() {
+        let store = store().await;
+        let clock = FixedClock(now());
+        let mut tui = QuLogTui::new(&store, &clock, Weekday::Mon, display())
+            .await
+            .unwrap();
+
+        let screen = screen(&mut tui);
+        assert!(screen.contains("Logs (3) · all"), "{}", screen);
+        assert!(screen.contains(" ops (1)"), "{}", screen);
+        assert!(screen.contains(" work (2)"), "{}", screen);
+
+        // Newest first, the first one selected
+        let review = screen.find("> [3] 05-14 : code review [work]").unwrap();
+        let climbing = screen.find("[2] 05-12 : went climbing [sport]").unwrap();
+        let deploy = screen
+            .find("[1] 04-14 : fixed the deploy script [work-ops]")
+            .unwrap();
+        assert!(review < climbing && climbing < deploy);
+    }
+
+    #[tokio::test]
+    async fn test_tui_filters() {
+        let store = store().await;
+        let clock = FixedClock(now());
+        let mut tui = QuLogTui::new(&store, &clock, Weekday::Mon, display())
+            .await
+            .unwrap();
+
+        press(&mut tui, "/DEPLOY").await;
+        let found = screen(&mut tui);
+        assert!(found.contains("Logs (1)"), "{}", found);
+        assert!(found.contains("/DEPLOY"), "{}", found);
+        press_code(&mut tui, KeyCode::Esc).await;
+        assert_eq!(tui.visible().len(), 3);
+
+        // Sidebar tags are sorted, the second one is sport
+        press_code(&mut tui, KeyCode::Tab).await;
+        press(&mut tui, "j ").await;
+        let sport = screen(&mut tui);
+        assert!(sport.contains("*sport (1)"), "{}", sport);
+        assert!(sport.contains("Logs (1)"), "{}", sport);
+        press(&mut tui, " ").await;
+        assert_eq!(tui.visible().len(), 3);
+
+        // all -> today -> yesterday -> this-week
+        press(&mut tui, "d").await;
+        assert!(screen(&mut tui).contains("Logs (1) · today"));
+        press(&mut tui, "dd").await;
+        let week = screen(&mut tui);
+        assert!(week.contains("Logs (2) · this-week"), "{}", week);
+        assert!(!week.contains("ops"), "{}", week);
+        press(&mut tui, "DDD").await;
+        assert!(screen(&mut tui).contains("Logs (3) · all"));
+    }
+
+    #[tokio::test]
+    async fn test_tui_edit_and_delete() {
+        let store = store().await;
+        let clock = FixedClock(now());
+        let mut tui = QuLogTui::new(&store, &clock, Weekday::Mon, display())
+            .await
+            .unwrap();
+
+        // "code review" is selected, replace "review" with "reviews"
+        press(&mut tui, "e").await;
+        assert!(screen(&mut tui).contains("Edit log 3"));
+        press(&mut tui, "s").await;
+        press_code(&mut tui, KeyCode::Enter).await;
+        let edited = screen(&mut tui);
+        assert!(
+            edited.contains("code reviews [work] (edited)"),
+            "{}",
+            edited
+        );
+        assert!(edited.contains("Log 3 saved"), "{}", edited);
+
+        // Escape leaves the log as it is
+        press(&mut tui, "e").await;
+        press_code(&mut tui, KeyCode::Backspace).await;
+        press_code(&mut tui, KeyCode::Esc).await;
+        let log = store
+            .query(&QuLogFilter::builder().ids([3]).build())
+            .await
+            .unwrap();
+        assert_eq!(log[0].text, "code reviews");
+
+        press(&mut tui, "jx").await;
+        assert!(screen(&mut tui).contains("Delete log 2? y/n"));
+        press(&mut tui, "n").await;
+        assert_eq!(tui.visible().len(), 3);
+        press(&mut tui, "xy").await;
+        let deleted = screen(&mut tui);
+        assert!(deleted.contains("Log 2 deleted"), "{}", deleted);
+        assert!(!deleted.contains("sport"), "{}", deleted);
+        assert_eq!(store.count(&QuLogFilter::default()).await.unwrap(), 2);
+
+        press(&mut tui, "q").await;
+        assert!(tui.quit);
+    }
+}
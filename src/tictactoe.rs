@@ -37,10 +37,7 @@ impl GameCommand {
             if !(1..=3).contains(&x) || !(1..=3).contains(&y) {
                 return None;
             }
-            match GameCommandMark::from(chars.next().unwrap()) {
-                Some(mark) => return Some(GameCommand::Mark(mark, x, y)),
-                None => return None,
-            }
+            GameCommandMark::from(chars.next().unwrap()).map(|mark| GameCommand::Mark(mark, x, y))
         }
 
         match string.trim().to_lowercase().as_str() {
@@ -93,11 +90,10 @@ impl GameCommandMark {
 
 impl PartialEq for GameCommandMark {
     fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (GameCommandMark::X, GameCommandMark::X) => true,
-            (GameCommandMark::O, GameCommandMark::O) => true,
-            _ => false,
-        }
+        matches!(
+            (self, other),
+            (GameCommandMark::X, GameCommandMark::X) | (GameCommandMark::O, GameCommandMark::O)
+        )
     }
 }
 
@@ -144,11 +140,8 @@ pub fn run_tictactoe() {
                         game_state = GameState::Terminated;
                     }
                 }
-                match is_somebody_won(&table) {
-                    Some(mark) => {
-                        game_state = GameState::Finished(Some(mark));
-                    }
-                    None => (),
+                if let Some(mark) = is_somebody_won(&table) {
+                    game_state = GameState::Finished(Some(mark));
                 }
             }
             GameState::New => {
@@ -167,10 +160,7 @@ for restarting enter restart.
                 match winner {
                     Some(winner) => {
                         println!("----------------------------");
-                        println!(
-                            ">>> PLAYER {} WON THE GAME! <<<",
-                            winner.to_char().to_string()
-                        );
+                        println!(">>> PLAYER {} WON THE GAME! <<<", winner.to_char());
                         game_state = GameState::New;
                         println!("----------------------------");
                     }
@@ -188,14 +178,14 @@ fn display_tictactoe(table: &Table) {
         for col in row {
             print!("{col}\t")
         }
-        print!("\n")
+        println!()
     }
 }
 
 fn read_input(player_mark: &GameCommandMark) -> String {
     println!(
         "Player {} Enter the input! (format: xy, like: 21)",
-        player_mark.to_char().to_string()
+        player_mark.to_char()
     );
     let mut input = String::new();
     std::io::stdout().flush().unwrap();
@@ -211,9 +201,9 @@ fn is_somebody_won(table: &Table) -> Option<GameCommandMark> {
             str2.to_lowercase().as_str(),
             str3.to_lowercase().as_str(),
         ) {
-            ("x", "x", "x") => return Some(GameCommandMark::X),
-            ("o", "o", "o") => return Some(GameCommandMark::O),
-            _ => return None,
+            ("x", "x", "x") => Some(GameCommandMark::X),
+            ("o", "o", "o") => Some(GameCommandMark::O),
+            _ => None,
         }
     }
 
@@ -224,8 +214,8 @@ fn is_somebody_won(table: &Table) -> Option<GameCommandMark> {
         }
     }
 
-    for i in 0..3 {
-        match equal3(&table[0][i], &table[1][i], &table[2][i]) {
+    for ((top, middle), bottom) in table[0].iter().zip(&table[1]).zip(&table[2]) {
+        match equal3(top, middle, bottom) {
             Some(mark) => return Some(mark),
             None => continue,
         }
@@ -240,7 +230,7 @@ fn is_somebody_won(table: &Table) -> Option<GameCommandMark> {
         _ => (),
     }
 
-    return None;
+    None
 }
 
 #[cfg(test)]